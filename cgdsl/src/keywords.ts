@@ -7,14 +7,15 @@ export const controlKeywords = [
   "conditional",
   "else",
   "trigger",
+  "betting",
 ];
 
 export const helperKeywords = [
   "for", "is", "of", "in", "on", "as",
   "to", "using", "with", "where",
   "until", "from", "at", "cards", "out",
-  "owner", "size", "fail", "successful",
-  "table", "times"
+  "owner", "size", "tokens", "fail", "successful",
+  "table", "times", "minimum", "trump"
 ];
 
 export const actionKeywords = [
@@ -27,7 +28,7 @@ export const actionKeywords = [
 
 export const filterKeywords = [
   "adjacent", "distinct", "empty", "higher",
-  "lower", "same",
+  "lower", "same", "follow",
 ];
 
 export const quantifierKeywords = [
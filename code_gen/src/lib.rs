@@ -60,8 +60,8 @@ pub fn spanned_ast(_: TokenStream, item: TokenStream) -> TokenStream {
 
                 // Clone and rewrite fields
                 let mut spanned_struct = s.clone();
-                // 1. Scrub the Arbitrary derive
-                scrub_arbitrary_derive(&mut spanned_struct.attrs);
+                // 1. Scrub the Arbitrary and JsonSchema derive
+                scrub_lowered_derives(&mut spanned_struct.attrs);
                 // 2. Span the fields
                 span_fields(&mut spanned_struct.fields);
                 spanned_items.push(Item::Struct(spanned_struct));
@@ -90,7 +90,7 @@ pub fn spanned_ast(_: TokenStream, item: TokenStream) -> TokenStream {
                 let name = &e.ident;
 
                 let mut spanned_enum = e.clone();
-                scrub_arbitrary_derive(&mut spanned_enum.attrs);
+                scrub_lowered_derives(&mut spanned_enum.attrs);
                 for variant in &mut spanned_enum.variants {
                     span_fields(&mut variant.fields);
                 }
@@ -406,9 +406,10 @@ fn generate_enum_walk(
 // Remove Traits that clash with the generated Spanned AST
 // E.g. Arbitrary clashes and is not necessary to have for the Spanned AST
 // (only the unspanned/lowered AST has trait Arbitrary!)
+// Same for JsonSchema: only the lowered AST is given to the backend.
 // ===========================================================================
 // ===========================================================================
-fn scrub_arbitrary_derive(attrs: &mut Vec<syn::Attribute>) {
+fn scrub_lowered_derives(attrs: &mut Vec<syn::Attribute>) {
     attrs.retain_mut(|attr| {
         if attr.path().is_ident("derive") {
             if let Ok(nested) = attr.parse_args_with(
@@ -416,7 +417,7 @@ fn scrub_arbitrary_derive(attrs: &mut Vec<syn::Attribute>) {
             ) {
                 let filtered: Vec<_> = nested
                     .into_iter()
-                    .filter(|path| !path.is_ident("Arbitrary"))
+                    .filter(|path| !path.is_ident("Arbitrary") && !path.is_ident("JsonSchema"))
                     .collect();
 
                 if filtered.is_empty() {
@@ -26,6 +26,7 @@ use code_gen::*;
 #[spanned_ast]
 pub mod ast {
     use arbitrary::Arbitrary;
+    use schemars::JsonSchema;
     use serde::{Serialize, Deserialize};
     // For generating Arbitrary ASTs (for Testing)
     use crate::arbitrary::{
@@ -50,7 +51,7 @@ pub mod ast {
     /// 
     /// These map directly to standard mathematical symbols used in expressions
     /// like `1 + 1` or `x * y`.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum IntOp {
         /// Addition (`+`)
         Plus,
@@ -68,7 +69,7 @@ pub mod ast {
     ///
     /// These operators are used in conditional expressions to compare two 
     /// integers and return a boolean result.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum IntCompare {
         /// Equality (`==`)
         Eq,
@@ -100,7 +101,7 @@ pub mod ast {
     /// ```
     /// 
     /// These Collections are allowed to have an arbitrary 'Owner'.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum UseMemory {
         /// Single Memory-Name (without owner) 
         Memory { 
@@ -132,7 +133,7 @@ pub mod ast {
     /// ```
     /// 
     /// => This is an IntCollection and not a IntExpr!
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum UseSingleMemory {
         /// Single Memory-Name (without owner) 
         Memory { 
@@ -158,7 +159,7 @@ pub mod ast {
     /// You can switch out 'max' with 'highest' and 'min' with lowest
     /// because they have the exact same semantic meaning and having two
     /// Extrema-Enums that do the same thing is unnecessary.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Extrema {
         /// Minimum/Lowest
         Min,
@@ -187,7 +188,7 @@ pub mod ast {
     /// 'out of game' and 'out of game fail' have different semantic
     /// meaning for 'BoolExpr':
     /// 'out of game' means all players that are out of game fail or successful.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum OutOf {
         /// Current Stage (the 'deepest' stage in which you are at the moment)
         CurrentStage,
@@ -213,7 +214,7 @@ pub mod ast {
     /// move 1 from ExampleLocation to ExampleLocation
     /// move 1 from ( Loc1, Loc2 ) to ExampleLocation
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Groupable {
         /// Single Location
         Location { 
@@ -232,7 +233,7 @@ pub mod ast {
     /// 
     /// If we would use '&(I:IntMemory of all)' it would have a different meaning 
     /// (IntCollection and not IntExpr).
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum SingleOwner {
         /// Onwer is Player
         Player { player: PlayerExpr },
@@ -248,7 +249,7 @@ pub mod ast {
     /// &(I:IntMemory of all)
     /// ```
     /// Even though we use the Memory of an Int the semantic meaning is an IntCollection.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum MultiOwner {
         /// Multiple Player 
         PlayerCollection { player_collection: Box<PlayerCollection>},
@@ -268,7 +269,7 @@ pub mod ast {
     /// location ExampleLocation on ( T:Team1,  team of next )
     /// location ExampleLocation on table
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Owner {
         /// Player
         Player { player: PlayerExpr },
@@ -294,7 +295,7 @@ pub mod ast {
     /// move >= 3 from ExampleLocation to ExampleLocation1
     /// move 3 from ExampleLocation to ExampleLocation1
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Quantity {
         /// Int
         Int {int: IntExpr},
@@ -315,7 +316,7 @@ pub mod ast {
     /// 
     /// There is not specified which operator binds stronger and there are also no '(' ')' given
     /// to give a specific range. -> If needed then implement it.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum IntRangeOperator {
         /// And (Used like Bool-And)
         And,
@@ -330,7 +331,7 @@ pub mod ast {
     /// ```text
     /// move >= 3 and <= 10 from ExampleLocation to ExampleLocation1
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct IntRange {
         pub start: (IntCompare, IntExpr),
         #[arbitrary(with = gen_vec_min_1)]
@@ -347,7 +348,7 @@ pub mod ast {
     /// ```
     /// 
     /// It is possible to extend this to other Collections and/or Rules.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Quantifier {
         /// All
         All,
@@ -369,7 +370,7 @@ pub mod ast {
     /// There are two types of End-Conditions:
     /// - **Bool**: breaks after a condition is not fullfilled
     /// - **Repetitions**: breaks if a certain number of iterations has been reached.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum EndCondition {
         /// Break with Bool
         UntilBool {bool_expr: BoolExpr},
@@ -390,7 +391,7 @@ pub mod ast {
     ///     ...
     /// }
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct Repititions {
         /// Int
         pub times: IntExpr,
@@ -404,7 +405,7 @@ pub mod ast {
     /// ```text
     /// memory ExampleMemory 3 on current
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum MemoryType {
         /// Int-Memory
         Int {int: IntExpr},
@@ -437,7 +438,7 @@ pub mod ast {
     /// end game with winner current
     /// end game with winner ( P:Player1, P:Player2 )
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Players {
         /// Player
         Player { player: PlayerExpr},
@@ -445,11 +446,26 @@ pub mod ast {
         PlayerCollection {player_collection: PlayerCollection},
     }
 
+    /// Teams is a wrapper for TeamExpr and TeamCollection (the Team-version of Players).
+    /// 
+    /// # Example
+    /// ```text
+    /// end game with winner T:Team1
+    /// end game with winner ( T:Team1, team of current )
+    /// ```
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
+    pub enum Teams {
+        /// Team
+        Team { team: TeamExpr},
+        /// TeamCollection
+        TeamCollection {team_collection: TeamCollection},
+    }
+
     /// There are certain things to end in a game:
     /// - **Turn**: Ending a turn of the current Player
     /// - **Current-Stage**: Ending the Current-Stage for everyone
     /// - **Stage**: Ending a specific Stage for everyone
-    /// - **Game**: Ending the Game with a specific Player or PlayerCollection
+    /// - **Game**: Ending the Game with a specific Player or PlayerCollection (or Team/TeamCollection)
     /// 
     /// # Example
     /// ```text
@@ -457,8 +473,9 @@ pub mod ast {
     /// end stage
     /// end ExampleStage
     /// end game with winner ( P:Player1, P:Player2 )
+    /// end game with winner T:Team1
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum EndType {
         /// Turn of the current Player
         Turn,
@@ -471,6 +488,8 @@ pub mod ast {
         },
         /// Game with a specific set of Winners
         GameWithWinner {players: Players},
+        /// Game with a specific set of winning Teams (every member wins)
+        GameWithTeamWinner {teams: Teams},
     }
 
     /// Certain information is sometimes required of a player.
@@ -487,7 +506,7 @@ pub mod ast {
     /// demand Suite of top(Stock)
     /// demand &I:ScoreMemory of current
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum DemandType {
         /// Demanding a CardPosition
         CardPosition {card_position: CardPosition},
@@ -509,7 +528,7 @@ pub mod ast {
     /// Rank(Ace, Two, Three, Four)
     /// for Suite(Clubs, Spades, Hearts, Diamonds)
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct Types {
         /// The types are sorted by Vec < (Key) -> (Values) >:
         /// (Key) -> (Values) for (Key) -> (Values) for ...
@@ -537,7 +556,7 @@ pub mod ast {
     /// previous
     /// competitor
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum RuntimePlayer {
         /// The Player that is currently playing
         Current,
@@ -556,7 +575,7 @@ pub mod ast {
     /// turnorder[3]
     /// &PC:PlayerColMemory[0]
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum QueryPlayer {
         /// Turn-Order in the Game
         Turnorder {int: IntExpr},
@@ -570,7 +589,7 @@ pub mod ast {
     /// ```text
     /// owner of max Hand using points ExamplePointMap
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum AggregatePlayer {
         /// Owner of a specific CardPosition
         OwnerOfCardPostion {card_position: Box<CardPosition>},
@@ -591,7 +610,7 @@ pub mod ast {
     /// owner of max Hand using points ExamplePointMap
     /// &P:PlayerMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum PlayerExpr {
         /// Name/Identifier of a Player
         Literal { 
@@ -616,7 +635,7 @@ pub mod ast {
     /// ```text
     /// ( 1, 2, 3, 4 )[0]
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum QueryInt {
         /// Element at Index of IntCollection
         IntCollectionAt { int_collection: Box<IntCollection>, int_expr: Box<IntExpr> },
@@ -631,8 +650,9 @@ pub mod ast {
     /// sum of Hand using ExamplePointMap
     /// max of Hand using ExamplePointMap
     /// max of ( 1, 2, 3 )
+    /// tokens Chips in Pot of current
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum AggregateInt {
         /// Size of a Collection e.g. CardSet, PlayerCollection
         SizeOf {collection: Collection},
@@ -653,6 +673,12 @@ pub mod ast {
         },
         /// Extrema of an IntCollection
         ExtremaIntCollection {extrema: Extrema, int_collection: IntCollection},
+        /// Number of Tokens on a Location
+        TokenCount {
+            #[arbitrary(with = gen_ident)]
+            token: String,
+            token_loc: Box<TokenLocExpr>,
+        },
     }
 
     /// An IntExpr that can be defined at runtime.
@@ -662,7 +688,7 @@ pub mod ast {
     /// stageroundcounter
     /// stageroundcounter(ExampleStage)
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum RuntimeInt {
         /// Round-Counter of the Current Stage
         CurrentStageRoundCounter,
@@ -683,7 +709,7 @@ pub mod ast {
     /// stageroundcounter
     /// &I:ExampleIntMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum IntExpr {
         /// i32
         Literal { int: i32},
@@ -712,7 +738,7 @@ pub mod ast {
     /// Suite of top(Hand)
     /// ( "Clubs", "Hearts" )[0]
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum QueryString {
         /// Key of a CardPosition
         KeyOf{ 
@@ -732,7 +758,7 @@ pub mod ast {
     /// Suite of top(Hand)
     /// ( "Clubs", "Hearts" )[0]
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum StringExpr {
         /// A Value for Example 'Ace' in Rank(Ace, Two, ...)
         Literal { value: String },
@@ -752,7 +778,7 @@ pub mod ast {
     /// Hand == Stock
     /// Hand != Stock
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum CardSetCompare {
         /// ==
         Eq,
@@ -767,7 +793,7 @@ pub mod ast {
     /// "Ace" == "Ace"
     /// "Ace" != "Ace"
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum StringCompare {
         /// ==
         Eq,
@@ -782,7 +808,7 @@ pub mod ast {
     /// current == next
     /// next != previous
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum PlayerCompare {
         /// ==
         Eq,
@@ -797,7 +823,7 @@ pub mod ast {
     /// T:T1 == T:T2
     /// T:T1 != T:T2
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum TeamCompare {
         /// ==
         Eq,
@@ -812,7 +838,7 @@ pub mod ast {
     /// and
     /// or
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum BoolOp {
         /// Bool-And
         And,
@@ -826,7 +852,7 @@ pub mod ast {
     /// ```text
     /// not
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum UnaryOp {
         /// Bool-Not
         Not,
@@ -842,7 +868,7 @@ pub mod ast {
     /// P:P1 != P:P2
     /// T:T1 != T:T2
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum CompareBool {
         /// Int-Comparison
         Int { int: IntExpr, cmp: IntCompare, int1: IntExpr },
@@ -867,7 +893,7 @@ pub mod ast {
     /// Hand not empty
     /// current out of game successful
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum AggregateBool {
         /// Comparisons
         Compare { cmp_bool: CompareBool},
@@ -891,7 +917,7 @@ pub mod ast {
     /// not Hand empty
     /// 1 == 2
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum BoolExpr {
         /// Binary Bool Operation
         Binary{ bool_expr: Box<BoolExpr>, op: BoolOp, bool_expr1: Box<BoolExpr>},
@@ -910,7 +936,7 @@ pub mod ast {
     /// ```text
     /// team of current
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum AggregateTeam {
         /// Team of a Player
         TeamOf { player: PlayerExpr },
@@ -924,7 +950,7 @@ pub mod ast {
     /// team of current
     /// &T:ExampleMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum TeamExpr {
         /// Name/Identifier of Team
         Literal {
@@ -950,7 +976,7 @@ pub mod ast {
     /// top(Hand)
     /// bottom(Hand)
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum QueryCardPosition {
         /// At a certain index of a Location
         At {
@@ -971,13 +997,21 @@ pub mod ast {
     }
 
     /// Sometimes we want to get the Position of the highest or lowest Card in a Location.
+    ///
+    /// The winner of a trick is the highest trump (a Card with the Key of `trump`)
+    /// if there is one, otherwise the highest Card that follows the lead (a Card with
+    /// the Key of `follow`). Cards that do neither can not win. Between equal Cards
+    /// the first one in the CardSet wins. With one trick Location per Player
+    /// `owner of winner of ...` is the Player that won (and leads the next trick).
     /// 
     /// # Example
     /// ```text
     /// max of Hand using ExamplePrecedence
     /// max of Hand using ExamplePointMap
+    /// winner of Trick of all using RankOrder follow Suite is &S:Lead
+    /// winner of Trick of all using RankOrder trump "Hearts" follow Suite is &S:Lead
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum AggregateCardPosition {
         /// Extrema of a CardSet using a PointMap
         ExtremaPointMap { extrema: Extrema, card_set: Box<CardSet>, 
@@ -989,6 +1023,23 @@ pub mod ast {
             #[arbitrary(with = gen_ident)]
             precedence: String 
         },
+        /// Winner of a trick (CardSet) following the lead Value of a Key
+        TrickWinner { card_set: Box<CardSet>,
+            #[arbitrary(with = gen_ident)]
+            precedence: String,
+            #[arbitrary(with = gen_ident)]
+            key: String,
+            lead: Box<StringExpr>
+        },
+        /// Winner of a trick (CardSet) following the lead Value of a Key with a trump Value
+        TrickWinnerTrump { card_set: Box<CardSet>,
+            #[arbitrary(with = gen_ident)]
+            precedence: String,
+            trump: Box<StringExpr>,
+            #[arbitrary(with = gen_ident)]
+            key: String,
+            lead: Box<StringExpr>
+        },
     }
 
     /// CardPosition is part of the 'bigger' CardSet-logic.
@@ -998,7 +1049,7 @@ pub mod ast {
     /// top(Hand)
     /// max of Hand using ExamplePrecedence
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum CardPosition {
         /// Query from the CardPosition
         Query { query: QueryCardPosition },
@@ -1017,7 +1068,7 @@ pub mod ast {
     /// face down
     /// private
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Status {
         /// Information/Card is public
         FaceUp,
@@ -1049,7 +1100,7 @@ pub mod ast {
     /// ( T:Team1, team of current )
     /// Hand of current
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Collection {
         IntCollection { int: IntCollection },
         StringCollection { string: StringCollection },
@@ -1067,7 +1118,7 @@ pub mod ast {
     /// &( I:ExampleIntMemory of all )
     /// &IC:ExampleMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum IntCollection {
         /// A list of IntExpr
         Literal {     
@@ -1094,7 +1145,7 @@ pub mod ast {
     /// &( S:ExampleStringMemory of all )
     /// &SC:ExampleMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum StringCollection {
         /// A list of StringExpr
         Literal { 
@@ -1120,7 +1171,7 @@ pub mod ast {
     /// ( Hand, Stock, Garbage )
     /// &LC:ExampleMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum LocationCollection {
         /// A list of Location
         Literal { 
@@ -1143,7 +1194,7 @@ pub mod ast {
     /// playersout
     /// others
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum RuntimePlayerCollection {
         /// All players that are still in the game
         PlayersOut,
@@ -1163,7 +1214,7 @@ pub mod ast {
     /// all
     /// any
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum AggregatePlayerCollection {
         /// All / Any
         Quantifier { quantifier: Quantifier },
@@ -1182,7 +1233,7 @@ pub mod ast {
     /// &(P:ExamplePlayerMemory of all)
     /// &PC:ExampleMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum PlayerCollection {
         /// A list of PlayerExpr
         Literal { players: Vec<PlayerExpr> },
@@ -1206,7 +1257,7 @@ pub mod ast {
     /// ```text
     /// other teams
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum RuntimeTeamCollection {
         /// All other Teams that are not the team of the current Player
         OtherTeams,
@@ -1221,7 +1272,7 @@ pub mod ast {
     /// &(T:ExampleTeamMemory of all)
     /// &TC:ExampleMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum TeamCollection {
         /// A list of TeamExpr
         Literal { 
@@ -1253,7 +1304,7 @@ pub mod ast {
     /// Hand of current
     /// &CS:ExampleMemory
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum CardSet {
         /// Singular Group of Cards without specifying the Owner
         Group { group: Group },
@@ -1275,7 +1326,7 @@ pub mod ast {
     /// Pair in Hand
     /// top(Hand)
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum Group {
         /// Location / LocaitonCollection
         Groupable { groupable: Groupable },
@@ -1305,10 +1356,11 @@ pub mod ast {
     /// Rank lower than "Ace" using ExamplePrecedence
     /// Suite is Suite of top(Hand)
     /// Suite is not Suite of top(Hand)
+    /// follow Suite is &S:Lead
     /// ExampleCombo
     /// not ExampleCombo
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum AggregateFilter {
         /// Aggregate CardSets with a specific size(-range)
         Size { cmp: IntCompare, int_expr: Box<IntExpr> },
@@ -1357,6 +1409,13 @@ pub mod ast {
             key: String,
             string: Box<StringExpr>
         },
+        /// Aggregate all Cards with Key is (equal) to a certain StringExpr if there is
+        /// one, otherwise all Cards (e.g. "must follow suit if possible")
+        FollowKey{ 
+            #[arbitrary(with = gen_ident)]
+            key: String,
+            string: Box<StringExpr>
+        },
         /// Aggregate all Cards that fulfill the combo
         Combo {
             #[arbitrary(with = gen_ident)]
@@ -1376,7 +1435,7 @@ pub mod ast {
     /// and
     /// or
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum FilterOp {
         /// "And" can be seen as building a cut of the two sets
         And,
@@ -1391,7 +1450,7 @@ pub mod ast {
     /// same Rank
     /// ( same Rank with size >= 3 )
     /// ```
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
     pub enum FilterExpr {
         /// Aggregate Logic
         Aggregate {aggregate: AggregateFilter},
@@ -1413,7 +1472,7 @@ pub mod ast {
     /// A `Game` is defined as a linear sequence of execution blocks. 
     /// The engine processes these flows in order, effectively running 
     /// the "script" of the game's life from initialization to conclusion.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct Game {
         /// The top-level sequence of stages, rules, and logic that 
         /// constitute the gameplay.
@@ -1425,7 +1484,7 @@ pub mod ast {
     /// A `FlowComponent` can be a high-level container (like a Stage), 
     /// a branching logic gate (like If or Choice), or a terminal 
     /// instruction (GameRule).
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum FlowComponent {
         /// A turn-based container for sequential player actions.
         SeqStage { stage: SeqStage },
@@ -1449,6 +1508,9 @@ pub mod ast {
         /// A race-condition trigger for simultaneous phases.
         TriggerRule { trigger_rule: TriggerRule },
 
+        /// A betting round (e.g. poker, Skat bidding).
+        BettingRound { betting_round: BettingRound },
+
         /// A complex multi-branch if-else-if structure.
         Conditional { conditional: Conditional },
     }
@@ -1458,7 +1520,7 @@ pub mod ast {
     /// `SetUpRule` defines the "World State" before any gameplay occurs. It 
     /// populates the game environment with players, cards, tokens, and 
     /// initial memory values.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum SetUpRule {
         /// Create a group of Players that are in the game
         CreatePlayer { 
@@ -1535,7 +1597,7 @@ pub mod ast {
     /// These rules represent "non-constructive" permutations—they modify, 
     /// move, or reassign existing game data rather than instantiating 
     /// new objects.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum ActionRule {
         /// Updates the [`Status`] of all cards within a [`CardSet`].
         /// Used for actions like "Reveal Hand" or "Face Down All Cards."
@@ -1602,7 +1664,7 @@ pub mod ast {
     ///
     /// `ScoringRule` acts as the "Referee" of the state machine, translating 
     /// game actions into competitive results.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum ScoringRule {
         /// Permutate the Score
         ScoreRule{score_rule: ScoreRule},
@@ -1615,7 +1677,7 @@ pub mod ast {
     /// Unlike structural rules (like `If` or `Conditional`), these variants 
     /// represent the final execution leaf nodes that actually modify 
     /// data within the game session.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum GameRule {
         SetUp { setup: SetUpRule},
         Action{ action: ActionRule},
@@ -1627,7 +1689,7 @@ pub mod ast {
     /// A `SeqStage` functions as a standard control loop where a single player 
     /// interacts with the game state. Unlike a [`SimStage`], this environment 
     /// is deterministic and synchronous, typically representing a "Turn."
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct SeqStage {
         /// Name/Identifier
         #[arbitrary(with = gen_ident)]
@@ -1645,7 +1707,7 @@ pub mod ast {
     /// the [`PlayerCollection`] can trigger the included `flows` at any time 
     /// (subject to rules like [`TriggerRule`]). The stage persists until the 
     /// [`EndCondition`] evaluates to true.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct SimStage {
         /// Name/Identifier
         #[arbitrary(with = gen_ident)]
@@ -1662,7 +1724,7 @@ pub mod ast {
     /// A `Case` can either be guarded by a logical condition or act as an 
     /// unconditional "else" block that captures any execution flow not 
     /// handled by previous cases.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum Case {
         NoBool{
             #[arbitrary(with = gen_flows_safe)]
@@ -1689,7 +1751,7 @@ pub mod ast {
     ///    flows are executed.
     /// 4. **Termination:** Execution exits the `Conditional` block immediately 
     ///    after one branch is taken (preventing multiple branches from firing).
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct Conditional {
         #[arbitrary(with = gen_vec_min_1)]
         pub cases: Vec<Case>,
@@ -1707,7 +1769,7 @@ pub mod ast {
     ///    * If `true`: The sequence of [`FlowComponent`]s is executed.
     ///    * If `false`: The flows are skipped entirely, and the engine moves 
     ///      to the next rule.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct IfRule {
         pub condition: BoolExpr,
         #[arbitrary(with = gen_flows_safe)]
@@ -1726,7 +1788,7 @@ pub mod ast {
     ///    activate the `flows`.
     /// 2. **Decision:** The player either accepts (executing the flows) 
     ///    or declines (skipping to the next part of the turn).
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct OptionalRule {
         #[arbitrary(with = gen_flows_safe)]
         pub flows: Vec<FlowComponent>,
@@ -1745,7 +1807,7 @@ pub mod ast {
     /// 2. **Selection:** The player submits a choice index.
     /// 3. **Execution:** The engine executes the specific [`FlowComponent`] 
     ///    associated with that choice.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct ChoiceRule {
         #[arbitrary(with = gen_flows_safe)]
         pub options: Vec<FlowComponent>,
@@ -1764,18 +1826,64 @@ pub mod ast {
     /// 3. **Flow Execution:** Only the claimant executes the list of `flows`.
     /// 4. **Exclusion:** The trigger is marked as "resolved" or "empty" for 
     ///    all other participants.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub struct TriggerRule {
         #[arbitrary(with = gen_flows_safe)]
         pub flows: Vec<FlowComponent>,
     }
 
+    /// A betting round where the players raise a stake until everybody has matched it.
+    ///
+    /// The players act one after another in turn order (starting with `current`).
+    /// Every player that is still in the round either
+    /// - calls: matches the highest stake (a check if there is nothing to match),
+    /// - raises: matches the highest stake and raises it by at least the minimum (default 1) or
+    /// - folds: gives up and is out of the current stage (out of the game outside of a stage).
+    ///
+    /// ### Execution Logic
+    /// 1. **Stakes:** Every player starts the round with a stake of 0.
+    /// 2. **Settled:** The round ends when every player that is still in has acted
+    ///    since the last raise and has matched the highest stake (a player that can not
+    ///    match it goes all in), or when only one player is left.
+    /// 3. **Pot:** Token stakes are moved from the Location of the player to the pot
+    ///    as soon as they are placed. Memory stakes are stored in the Memory of the player.
+    ///
+    /// # Example
+    /// ```text
+    /// betting for playersin with Chips from Stack to Pot minimum 10
+    /// betting for (current, next) on Bid minimum 18
+    /// ```
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
+    pub enum BettingRound {
+        Bet { players: PlayerCollection, stake: Stake },
+        BetMinimum { players: PlayerCollection, stake: Stake, minimum: Box<IntExpr> },
+    }
+
+    /// What is at stake in a [`BettingRound`].
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
+    pub enum Stake {
+        /// Tokens that are moved from a Location of the player (e.g. Stack of current) to the pot
+        Tokens {
+            #[arbitrary(with = gen_ident)]
+            token: String,
+            #[arbitrary(with = gen_ident)]
+            from: String,
+            #[arbitrary(with = gen_ident)]
+            to: String,
+        },
+        /// A bid that is stored in a Memory of the player
+        Memory {
+            #[arbitrary(with = gen_ident)]
+            memory: String,
+        },
+    }
+
     /// A high-level dispatcher for all types of physical movement within the game.
     ///
     /// This enum acts as a container for specialized movement logic, allowing 
     /// the game engine to process different types of transfers (Cards vs. Tokens) 
     /// and different contexts (Dealing vs. Exchanging) through a unified interface.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum MoveType {
         Deal { deal: DealMove },
         Exchange { exchange: ExchangeMove},
@@ -1787,14 +1895,14 @@ pub mod ast {
     ///
     /// This structure is the core implementation for all card-based transitions, 
     /// allowing for both bulk moves and quantity-limited transfers.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum MoveCardSet {
         Move { from: CardSet, status: Status, to: CardSet },
         MoveQuantity { quantity: Quantity, from: CardSet, status: Status, to: CardSet},
     }
 
     /// Represents a standard, atomic transfer of a [`CardSet`] between two locations.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum ClassicMove {
         MoveCardSet {move_cs: MoveCardSet},
     }
@@ -1804,7 +1912,7 @@ pub mod ast {
     /// Dealing is a specialized movement that typically involves distributing 
     /// cards from a source (e.g., a "Deck" or "Stock") to one or more 
     /// players' private areas (e.g., "Hands").
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum DealMove {
         MoveCardSet {deal_cs: MoveCardSet},
     }
@@ -1815,7 +1923,7 @@ pub mod ast {
     /// an `ExchangeMove` is used for high-level operations involving defined 
     /// collections (e.g., trading a "Hand," moving a "Discard Pile," or 
     /// passing a "Draft Pack").
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum ExchangeMove {
         MoveCardSet {exchange_cs: MoveCardSet},
     }
@@ -1824,7 +1932,7 @@ pub mod ast {
     ///
     /// This supports both moving a single specific token and moving a 
     /// specific quantity of a token type.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum TokenMove {
         Place {
             #[arbitrary(with = gen_ident)]
@@ -1840,7 +1948,7 @@ pub mod ast {
     ///
     /// This enum specifies whether a token is located within a general game 
     /// structure or if it is tied to a specific player's context.
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum TokenLocExpr {
         Groupable{ groupable: Groupable},
         GroupablePlayers { groupable: Groupable, players: Players},
@@ -1850,8 +1958,16 @@ pub mod ast {
     ///
     /// Points can be attributed to an individual [`Player`] (e.g., for a leaderboard) 
     /// or stored within a [`Memory`] slot (e.g., a global counter or team-based 
-    /// shared score).
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    /// shared score). A Team has a score of its own, just like a Player.
+    ///
+    /// # Example
+    /// ```text
+    /// score 10 to current
+    /// score 10 to Tricks of current
+    /// score 10 to team of current
+    /// score 10 to Eyes of T:Re
+    /// ```
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum ScoreRule {
         Score {int: IntExpr, players: Players},
         ScoreMemory {int: IntExpr, 
@@ -1859,13 +1975,21 @@ pub mod ast {
             memory: String,
             players: Players
         },
+        /// Score of the Team(s)
+        ScoreTeams {int: IntExpr, teams: Teams},
+        /// Memory of the Team(s)
+        ScoreMemoryTeams {int: IntExpr, 
+            #[arbitrary(with = gen_ident)]
+            memory: String,
+            teams: Teams
+        },
     }
 
     /// Specifies the metric or attribute used to evaluate a win condition.
     ///
     /// This enum identifies which player-owned or global values should be 
     /// compared when determining the outcome of a game (e.g., via `WinnerWith`).
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum WinnerType {
         Score,
         Memory{ 
@@ -1879,10 +2003,33 @@ pub mod ast {
     ///
     /// This enum supports both explicit declarations (pointing to specific players) 
     /// and rule-based declarations (calculating winners based on game statistics).
-    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary)]
+    ///
+    /// Teams are compared by the value of the Team:
+    /// - **score**: the score of the Team plus the sum of the scores of its members,
+    /// - **Memory**: the Memory of the Team if the Memory is on Teams, otherwise the
+    ///   sum of the Memory of its members,
+    /// - **position**: the best position of its members.
+    ///
+    /// Ties are not broken: every Player/Team with the highest (lowest) value wins.
+    /// A Player wins if the Team of the Player wins. Game-specific tie-breakers
+    /// (e.g. Contra wins a Doppelkopf game at 120 to 120) are written as an if-rule
+    /// with an explicit `winner is T:Contra`.
+    ///
+    /// # Example
+    /// ```text
+    /// winner is current
+    /// winner is highest score
+    /// winner is T:Re
+    /// winner is highest team Eyes
+    /// ```
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Arbitrary, JsonSchema)]
     pub enum WinnerRule {
         Winner {players: Players},
         WinnerWith {extrema: Extrema, winner_type: WinnerType},
+        /// Explicit winning Team(s)
+        WinnerTeams {teams: Teams},
+        /// The Team(s) with the highest/lowest value
+        WinnerTeamWith {extrema: Extrema, winner_type: WinnerType},
     }
 
     // ===========================================================================
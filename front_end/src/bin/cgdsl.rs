@@ -0,0 +1,53 @@
+use front_end::ast::ast_spanned::SGame;
+use front_end::diff::diff_games;
+use front_end::validation::parse_document;
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: cgdsl diff <old.cgdsl> <new.cgdsl>";
+
+fn main() -> ExitCode {
+    let args: Vec<_> = env::args().collect();
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("diff") if args.len() == 4 => diff(&args[2], &args[3]),
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Exit code like diff: 0 = same game, 1 = different, 2 = error
+fn diff(old: &str, new: &str) -> ExitCode {
+    let (Some(old), Some(new)) = (read_game(old), read_game(new)) else {
+        return ExitCode::from(2);
+    };
+
+    let diff = diff_games(&old, &new);
+    if diff.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    print!("{}", diff);
+    ExitCode::from(1)
+}
+
+fn read_game(path: &str) -> Option<SGame> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return None;
+        }
+    };
+
+    match parse_document(&text) {
+        Ok(game) => Some(game),
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            None
+        }
+    }
+}
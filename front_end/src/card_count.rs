@@ -0,0 +1,847 @@
+///    Card counting for Locations (abstract interpretation over the IR).
+///
+///    `deal 12 from top(Stock) private to Hand of all` needs 36 cards in Stock
+///    if there are three players. The analysis keeps a lower and an upper bound
+///    (see Bounds) of the number of cards on every Location:
+///    - `card on Stock: ...` adds the cross product of the Types,
+///    - moves, deals and exchanges take cards from one Location and put them on another,
+///    - Conditions are ignored (every branch is possible).
+///
+///    A Location that exists more than once (`location Hand on all`) has one Bounds
+///    for all of its instances. A move that only changes some of them (`Hand of next`)
+///    joins the Bounds instead of replacing them, so such a Location can only be
+///    reported as maybe too small. Until the turn changes, the instance of the
+///    current player (`Hand`) has its own Bounds (see Env).
+///
+///    Stages with `n times` count their rounds exactly (see Rounds), so
+///    `stage Deal for current 1 times { deal ... }` only deals once.
+///    All other loops are widened: a falling lower bound drops to 0 and
+///    a growing upper bound becomes unbounded.
+///
+///    A rule that needs more cards than its Location has is reported.
+
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+
+use crate::ast::ast_spanned::*;
+use crate::ir::{Ir, Payload, SpannedPayload, StateID};
+use crate::spans::OwnedSpan;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CardCountWarning {
+    /// The Location never has enough cards for the rule.
+    NotEnoughCards {
+        location: String,
+        needed: u64,
+        available: u64,
+        span: OwnedSpan,
+    },
+    /// The Location can have too few cards (e.g. be empty) when the rule is played.
+    MaybeNotEnoughCards {
+        location: String,
+        needed: u64,
+        span: OwnedSpan,
+    },
+}
+
+impl fmt::Display for CardCountWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CardCountWarning::NotEnoughCards {
+                location,
+                needed,
+                available,
+                ..
+            } => write!(
+                f,
+                "Not enough cards: this needs {} card(s) from '{}', but it has at most {}",
+                needed, location, available
+            ),
+            CardCountWarning::MaybeNotEnoughCards {
+                location, needed, ..
+            } => write!(
+                f,
+                "'{}' may have fewer than {} card(s) here (e.g. be empty)",
+                location, needed
+            ),
+        }
+    }
+}
+
+impl CardCountWarning {
+    pub fn span(&self) -> &OwnedSpan {
+        match self {
+            CardCountWarning::NotEnoughCards { span, .. } => span,
+            CardCountWarning::MaybeNotEnoughCards { span, .. } => span,
+        }
+    }
+}
+
+pub fn card_count_analysis(fsm: &Ir<SpannedPayload>) -> Vec<CardCountWarning> {
+    let world = World::of(fsm);
+    let states = fixpoint(fsm, &world);
+
+    let mut warnings = Vec::new();
+    for (from, edges) in fsm.states.iter() {
+        // All rounds together (unreachable States are left out)
+        let Some(env) = states
+            .get(from)
+            .and_then(|partitions| partitions.values().cloned().reduce(|a, b| join_env(&a, &b)))
+        else {
+            continue;
+        };
+
+        for edge in edges.iter() {
+            let Payload::Action(rule) = &edge.payload else {
+                continue;
+            };
+            let GameRule::Action { action } = &rule.node else {
+                continue;
+            };
+            if let Some(warning) = world.card_move(action).and_then(|m| m.check(&env, &world))
+                && !warnings.contains(&warning)
+            {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    warnings
+}
+
+// ===========================================================================
+// Domain
+// ===========================================================================
+/// Lower and upper bound of a number of cards (or players).
+/// `hi == None` means unbounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Bounds {
+    lo: u64,
+    hi: Option<u64>,
+}
+
+impl Bounds {
+    const UNKNOWN: Bounds = Bounds { lo: 0, hi: None };
+
+    fn exact(n: u64) -> Self {
+        Bounds { lo: n, hi: Some(n) }
+    }
+
+    fn join(self, other: Bounds) -> Self {
+        Bounds {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.zip(other.hi).map(|(a, b)| a.max(b)),
+        }
+    }
+
+    fn add(self, other: Bounds) -> Self {
+        Bounds {
+            lo: self.lo.saturating_add(other.lo),
+            hi: self.hi.zip(other.hi).map(|(a, b)| a.saturating_add(b)),
+        }
+    }
+
+    /// Takes `other` away (never below 0).
+    fn sub(self, other: Bounds) -> Self {
+        Bounds {
+            lo: other.hi.map_or(0, |hi| self.lo.saturating_sub(hi)),
+            hi: self.hi.map(|hi| hi.saturating_sub(other.lo)),
+        }
+    }
+
+    fn mul(self, other: Bounds) -> Self {
+        Bounds {
+            lo: self.lo.saturating_mul(other.lo),
+            hi: self.hi.zip(other.hi).map(|(a, b)| a.saturating_mul(b)),
+        }
+    }
+
+    /// Everything that changed since `self` jumps to its limit.
+    fn widen(self, next: Bounds) -> Self {
+        let grows = match (self.hi, next.hi) {
+            (Some(old), Some(new)) => new > old,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        Bounds {
+            lo: if next.lo < self.lo { 0 } else { self.lo },
+            hi: if grows { None } else { self.hi },
+        }
+    }
+}
+
+/// Cards on every Location.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Env {
+    /// Location -> cards on each of its instances
+    locations: BTreeMap<String, Bounds>,
+    /// Location -> cards on the instance of the current player (missing = see locations).
+    /// Only for Locations that exist more than once, cleared when the turn can change.
+    current: BTreeMap<String, Bounds>,
+}
+
+impl Env {
+    /// Cards on the instance(s) of the Location the side stands for.
+    fn bounds(&self, side: &Side, location: &str) -> Option<Bounds> {
+        side.current
+            .then(|| self.current.get(location))
+            .flatten()
+            .or_else(|| self.locations.get(location))
+            .copied()
+    }
+
+    /// The instance(s) of the Location the side stands for have `bounds` cards now.
+    fn set(&mut self, world: &World, side: &Side, location: &str, bounds: Bounds) {
+        let Some(old) = self.locations.get_mut(location) else {
+            return;
+        };
+
+        if world.every_instance(side, location) {
+            *old = bounds;
+            self.current.remove(location);
+        } else {
+            // The other instances keep their cards
+            *old = old.join(bounds);
+            if side.current {
+                self.current.insert(location.to_string(), bounds);
+            } else {
+                self.current.remove(location);
+            }
+        }
+    }
+}
+
+/// Finished rounds of the stages with `n times` (missing = 0).
+/// Every State of the IR has one Env per Rounds.
+type Rounds = BTreeMap<String, u64>;
+
+fn join_env(a: &Env, b: &Env) -> Env {
+    let join = |a: &BTreeMap<String, Bounds>, b: &BTreeMap<String, Bounds>| {
+        a.iter()
+            .map(|(location, bounds)| {
+                let joined = b.get(location).map_or(*bounds, |other| bounds.join(*other));
+                (location.clone(), joined)
+            })
+            .collect()
+    };
+
+    Env {
+        locations: join(&a.locations, &b.locations),
+        // Known on both paths, otherwise the Bounds of all instances are used
+        current: join(&a.current, &b.current)
+            .into_iter()
+            .filter(|(location, _)| b.current.contains_key(location))
+            .collect(),
+    }
+}
+
+fn widen_env(old: &Env, next: &Env) -> Env {
+    let widen = |old: &BTreeMap<String, Bounds>, next: &BTreeMap<String, Bounds>| {
+        old.iter()
+            .filter_map(|(location, bounds)| {
+                let widened = bounds.widen(*next.get(location)?);
+                Some((location.clone(), widened))
+            })
+            .collect()
+    };
+
+    Env {
+        locations: widen(&old.locations, &next.locations),
+        current: widen(&old.current, &next.current),
+    }
+}
+
+// ===========================================================================
+// Fixpoint
+// ===========================================================================
+/// Number of joins on the same State before it is widened.
+const WIDEN_AFTER: usize = 3;
+
+fn fixpoint(fsm: &Ir<SpannedPayload>, world: &World) -> HashMap<StateID, HashMap<Rounds, Env>> {
+    let initial = Env {
+        locations: world
+            .instances
+            .keys()
+            .map(|location| (location.clone(), Bounds::exact(0)))
+            .collect(),
+        current: BTreeMap::new(),
+    };
+
+    let mut states: HashMap<StateID, HashMap<Rounds, Env>> = HashMap::new();
+    let mut joins: HashMap<(StateID, Rounds), usize> = HashMap::new();
+    states
+        .entry(fsm.entry)
+        .or_default()
+        .insert(Rounds::new(), initial);
+
+    let mut worklist = VecDeque::from([(fsm.entry, Rounds::new())]);
+    while let Some((state, rounds)) = worklist.pop_front() {
+        let env = states[&state][&rounds].clone();
+
+        for edge in fsm.states.get(&state).into_iter().flatten() {
+            let Some((next_rounds, next_env)) = world.transfer(&edge.payload, &rounds, &env) else {
+                continue;
+            };
+
+            let partitions = states.entry(edge.to).or_default();
+            let next_env = match partitions.get(&next_rounds) {
+                None => next_env,
+                Some(old) => {
+                    let count = joins.entry((edge.to, next_rounds.clone())).or_default();
+                    *count += 1;
+
+                    let mut joined = join_env(old, &next_env);
+                    if *count > WIDEN_AFTER {
+                        joined = widen_env(old, &joined);
+                    }
+                    if &joined == old {
+                        continue;
+                    }
+                    joined
+                }
+            };
+
+            partitions.insert(next_rounds.clone(), next_env);
+            worklist.push_back((edge.to, next_rounds));
+        }
+    }
+
+    states
+}
+
+// ===========================================================================
+// World
+// ===========================================================================
+/// Everything that does not change while the game is played.
+#[derive(Default)]
+struct World {
+    players: Option<u64>,
+    teams: Option<u64>,
+    /// Location -> how often it exists
+    instances: HashMap<String, Bounds>,
+    /// Stage -> `n` of `n times`
+    repetitions: HashMap<String, u64>,
+}
+
+impl World {
+    fn of(fsm: &Ir<SpannedPayload>) -> Self {
+        let mut world = World::default();
+        let mut locations = Vec::new();
+
+        for edge in fsm.states.values().flatten() {
+            match &edge.payload {
+                Payload::Action(rule) => {
+                    let GameRule::SetUp { setup } = &rule.node else {
+                        continue;
+                    };
+                    match &setup.node {
+                        SetUpRule::CreatePlayer { players } => {
+                            world.players = Some(world.players.unwrap_or(0) + players.len() as u64);
+                        }
+                        SetUpRule::CreateTeams { teams } => {
+                            world.teams = Some(world.teams.unwrap_or(0) + teams.len() as u64);
+                        }
+                        SetUpRule::CreateLocation {
+                            locations: names,
+                            owner,
+                        } => {
+                            locations.push((names, owner));
+                        }
+                        _ => {}
+                    }
+                }
+                Payload::EndCondition { expr, stage, .. } => {
+                    let reps = match &expr.node {
+                        EndCondition::UntilRep { reps } => reps,
+                        EndCondition::UntilBoolRep { reps, .. } => reps,
+                        _ => continue,
+                    };
+                    if let Some(n) = literal(&reps.node.times) {
+                        world.repetitions.insert(stage.node.clone(), n);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Needs the number of players and teams
+        for (names, owner) in locations {
+            let instances = world.owner_instances(owner);
+            for name in names.iter() {
+                world
+                    .instances
+                    .entry(name.node.clone())
+                    .and_modify(|b| *b = b.add(instances))
+                    .or_insert(instances);
+            }
+        }
+
+        world
+    }
+
+    /// Does the side stand for every instance of the Location (`Hand of all`, `Stock`)?
+    fn every_instance(&self, side: &Side, location: &str) -> bool {
+        side.instances.hi == Some(side.instances.lo)
+            && self.instances.get(location) == Some(&side.instances)
+    }
+
+    /// Only a Location that exists once can be reported as too small for sure.
+    fn single_instance(&self, location: &str) -> bool {
+        self.instances.get(location) == Some(&Bounds::exact(1))
+    }
+
+    fn players(&self) -> Bounds {
+        self.players.map_or(Bounds::UNKNOWN, Bounds::exact)
+    }
+
+    fn teams(&self) -> Bounds {
+        self.teams.map_or(Bounds::UNKNOWN, Bounds::exact)
+    }
+
+    fn owner_instances(&self, owner: &SOwner) -> Bounds {
+        match &owner.node {
+            Owner::Player { .. } | Owner::Team { .. } | Owner::Table => Bounds::exact(1),
+            Owner::PlayerCollection { player_collection } => {
+                self.player_collection_size(player_collection)
+            }
+            Owner::TeamCollection { team_collection } => match &team_collection.node {
+                TeamCollection::Literal { teams } => Bounds::exact(teams.len() as u64),
+                TeamCollection::Runtime { .. } => self.teams().sub(Bounds::exact(1)),
+                _ => Bounds::UNKNOWN,
+            },
+        }
+    }
+
+    fn player_collection_size(&self, player_collection: &SPlayerCollection) -> Bounds {
+        match &player_collection.node {
+            PlayerCollection::Literal { players } => Bounds::exact(players.len() as u64),
+            PlayerCollection::Aggregate { aggregate } => match &aggregate.node {
+                AggregatePlayerCollection::Quantifier { quantifier } => match quantifier.node {
+                    Quantifier::All => self.players(),
+                    Quantifier::Any => Bounds::exact(1),
+                },
+            },
+            PlayerCollection::Runtime { runtime } => match runtime.node {
+                RuntimePlayerCollection::PlayersIn | RuntimePlayerCollection::PlayersOut => {
+                    Bounds {
+                        lo: 0,
+                        hi: self.players().hi,
+                    }
+                }
+                RuntimePlayerCollection::Others => self.players().sub(Bounds::exact(1)),
+            },
+            _ => Bounds::UNKNOWN,
+        }
+    }
+
+    /// The Rounds and cards after the edge (None if the edge can not be taken).
+    fn transfer(
+        &self,
+        payload: &SpannedPayload,
+        rounds: &Rounds,
+        env: &Env,
+    ) -> Option<(Rounds, Env)> {
+        let mut rounds = rounds.clone();
+        let mut env = env.clone();
+
+        match payload {
+            Payload::Action(rule) => match &rule.node {
+                GameRule::SetUp { setup } => {
+                    if let SetUpRule::CreateCardOnLocation { location, cards } = &setup.node
+                        && let Some(bounds) = env.locations.get_mut(&location.node)
+                    {
+                        *bounds = bounds.add(Bounds::exact(card_count(cards)));
+                    }
+                }
+                GameRule::Action { action } => {
+                    if let Some(card_move) = self.card_move(action) {
+                        card_move.apply(&mut env, self);
+                    }
+                    if let ActionRule::CycleAction { .. } = &action.node {
+                        env.current.clear();
+                    }
+                }
+                GameRule::Scoring { .. } => {}
+            },
+            Payload::EndCondition {
+                expr,
+                negated,
+                stage,
+            } => {
+                let Some(n) = self.repetitions.get(&stage.node) else {
+                    return Some((rounds, env));
+                };
+                let done = rounds.get(&stage.node).copied().unwrap_or(0) >= *n;
+
+                // Which side of the condition depends on the rounds
+                let (end_needs_reps, continue_needs_reps) = match &expr.node {
+                    EndCondition::UntilRep { .. } => (true, true),
+                    EndCondition::UntilBoolRep { logic, .. } => match logic.node {
+                        BoolOp::And => (true, false),
+                        BoolOp::Or => (false, true),
+                    },
+                    _ => (false, false),
+                };
+
+                if *negated {
+                    if end_needs_reps && !done {
+                        return None;
+                    }
+                    // The next time the stage starts at 0
+                    rounds.remove(&stage.node);
+                } else if continue_needs_reps && done {
+                    return None;
+                }
+            }
+            Payload::StageRoundCounter(stage) => {
+                if let Some(n) = self.repetitions.get(&stage.node) {
+                    let round = rounds.entry(stage.node.clone()).or_default();
+                    *round = (*round + 1).min(*n);
+                }
+            }
+            Payload::EndStage(stage) => {
+                rounds.remove(&stage.node);
+            }
+            Payload::Condition { .. }
+            | Payload::Choice
+            | Payload::Optional
+            | Payload::Trigger { .. }
+            | Payload::Bet { .. } => {}
+        }
+
+        // Everything else can start another turn (or stage)
+        if !matches!(
+            payload,
+            Payload::Action(_) | Payload::Condition { .. } | Payload::Choice | Payload::Optional
+        ) {
+            env.current.clear();
+        }
+
+        Some((rounds, env))
+    }
+
+    fn card_move(&self, action: &SActionRule) -> Option<CardMove> {
+        let ActionRule::Move { move_type } = &action.node else {
+            return None;
+        };
+        let move_cs = match &move_type.node {
+            MoveType::Deal { deal } => match &deal.node {
+                DealMove::MoveCardSet { deal_cs } => deal_cs,
+            },
+            MoveType::Exchange { exchange } => match &exchange.node {
+                ExchangeMove::MoveCardSet { exchange_cs } => exchange_cs,
+            },
+            MoveType::Classic { classic } => match &classic.node {
+                ClassicMove::MoveCardSet { move_cs } => move_cs,
+            },
+            // Tokens are not cards
+            MoveType::Place { .. } => return None,
+        };
+
+        let (amount, from, to) = match &move_cs.node {
+            MoveCardSet::Move { from, to, .. } => (Amount::All, from, to),
+            MoveCardSet::MoveQuantity {
+                quantity, from, to, ..
+            } => (amount(quantity), from, to),
+        };
+
+        Some(CardMove {
+            from: self.side(from),
+            to: self.side(to),
+            amount,
+            span: from.span.clone(),
+        })
+    }
+
+    fn side(&self, card_set: &SCardSet) -> Side {
+        match &card_set.node {
+            CardSet::Group { group } => side_of_group(group, Bounds::exact(1), true),
+            CardSet::GroupOwner { group, owner } => {
+                side_of_group(group, self.owner_instances(owner), is_current(owner))
+            }
+            CardSet::Memory { .. } => Side {
+                locations: vec![],
+                instances: Bounds::UNKNOWN,
+                current: false,
+                part: Part::Subset,
+            },
+        }
+    }
+}
+
+fn is_current(owner: &SOwner) -> bool {
+    let Owner::Player { player } = &owner.node else {
+        return false;
+    };
+
+    matches!(
+        &player.node,
+        PlayerExpr::Runtime { runtime } if matches!(runtime.node, RuntimePlayer::Current)
+    )
+}
+
+fn literal(int: &SIntExpr) -> Option<u64> {
+    match &int.node {
+        IntExpr::Literal { int } => u64::try_from(int.node).ok(),
+        _ => None,
+    }
+}
+
+/// Cross product of every Types
+fn card_count(cards: &[STypes]) -> u64 {
+    cards
+        .iter()
+        .map(|types| {
+            types
+                .node
+                .types
+                .iter()
+                .map(|(_, values)| values.len() as u64)
+                .product::<u64>()
+        })
+        .sum()
+}
+
+// ===========================================================================
+// Moves
+// ===========================================================================
+/// How many cards are moved (per instance of the Location).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Amount {
+    /// Without a quantity or `all`
+    All,
+    Exact(u64),
+    /// `any` or `>= 3`
+    AtLeast(u64),
+    Unknown,
+}
+
+fn amount(quantity: &SQuantity) -> Amount {
+    match &quantity.node {
+        Quantity::Int { int } => literal(int).map_or(Amount::Unknown, Amount::Exact),
+        Quantity::Quantifier { quantifier } => match quantifier.node {
+            Quantifier::All => Amount::All,
+            Quantifier::Any => Amount::AtLeast(1),
+        },
+        Quantity::IntRange { int_range } => {
+            // With `or` the start does not have to hold
+            if int_range
+                .node
+                .op_int
+                .iter()
+                .any(|(op, _, _)| matches!(op.node, IntRangeOperator::Or))
+            {
+                return Amount::Unknown;
+            }
+
+            let (cmp, int) = &int_range.node.start;
+            match (&cmp.node, literal(int)) {
+                (IntCompare::Eq | IntCompare::Ge, Some(n)) => Amount::AtLeast(n),
+                (IntCompare::Gt, Some(n)) => Amount::AtLeast(n + 1),
+                _ => Amount::Unknown,
+            }
+        }
+    }
+}
+
+/// Which part of the Location is used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Part {
+    /// `Hand`
+    Whole,
+    /// `top(Hand)`, `Hand[3]`, `max of Hand using ...`
+    Position,
+    /// `Hand where ...`, `Pair in Hand`
+    Subset,
+}
+
+/// One side of a move.
+#[derive(Debug, Clone)]
+struct Side {
+    /// Empty if the Location is not known (Memory)
+    locations: Vec<String>,
+    instances: Bounds,
+    /// The instance of the current player (`Hand`, `Hand of current`)
+    current: bool,
+    part: Part,
+}
+
+fn side_of_group(group: &SGroup, instances: Bounds, current: bool) -> Side {
+    let (locations, part) = match &group.node {
+        Group::Groupable { groupable } => (groupable_locations(groupable), Part::Whole),
+        Group::Where { groupable, .. }
+        | Group::NotCombo { groupable, .. }
+        | Group::Combo { groupable, .. } => (groupable_locations(groupable), Part::Subset),
+        Group::CardPosition { card_position } => match &card_position.node {
+            CardPosition::Query { query } => match &query.node {
+                QueryCardPosition::At { location, .. }
+                | QueryCardPosition::Top { location }
+                | QueryCardPosition::Bottom { location } => {
+                    (vec![location.node.clone()], Part::Position)
+                }
+            },
+            CardPosition::Aggregate { aggregate } => {
+                let card_set = match &aggregate.node {
+                    AggregateCardPosition::ExtremaPointMap { card_set, .. } => card_set,
+                    AggregateCardPosition::ExtremaPrecedence { card_set, .. } => card_set,
+                    AggregateCardPosition::TrickWinner { card_set, .. } => card_set,
+                    AggregateCardPosition::TrickWinnerTrump { card_set, .. } => card_set,
+                };
+                let locations = match &card_set.node {
+                    CardSet::Group { group } | CardSet::GroupOwner { group, .. } => {
+                        side_of_group(group, instances, current).locations
+                    }
+                    CardSet::Memory { .. } => vec![],
+                };
+                (locations, Part::Position)
+            }
+        },
+    };
+
+    Side {
+        locations,
+        instances,
+        current,
+        part,
+    }
+}
+
+fn groupable_locations(groupable: &SGroupable) -> Vec<String> {
+    match &groupable.node {
+        Groupable::Location { name } => vec![name.node.clone()],
+        Groupable::LocationCollection {
+            location_collection,
+        } => match &location_collection.node {
+            LocationCollection::Literal { locations } => {
+                locations.iter().map(|l| l.node.clone()).collect()
+            }
+            LocationCollection::Memory { .. } => vec![],
+        },
+    }
+}
+
+/// A move, deal or exchange of cards.
+struct CardMove {
+    from: Side,
+    to: Side,
+    amount: Amount,
+    span: OwnedSpan,
+}
+
+impl CardMove {
+    /// The single Location the cards are taken from.
+    fn source(&self) -> Option<&String> {
+        match &self.from.locations[..] {
+            [location] => Some(location),
+            _ => None,
+        }
+    }
+
+    /// From one Location to multiple owners (`deal 12 ... to Hand of all`)
+    /// everybody gets the amount.
+    fn spread(&self) -> Bounds {
+        if self.from.instances == Bounds::exact(1) {
+            self.to.instances
+        } else {
+            Bounds::exact(1)
+        }
+    }
+
+    /// From multiple owners to one Location (`move Hand of all to Stock`)
+    /// the Location gets everything.
+    fn gather(&self) -> Bounds {
+        if self.to.instances == Bounds::exact(1) {
+            self.from.instances
+        } else {
+            Bounds::exact(1)
+        }
+    }
+
+    /// Needed and taken cards per target, if the source has `available` cards.
+    fn taken(&self, available: Bounds) -> (u64, Bounds) {
+        match (self.amount, self.from.part) {
+            (Amount::All, Part::Position) => (1, Bounds::exact(1)),
+            (Amount::All, Part::Whole) => (0, available),
+            (Amount::Exact(n), _) => (n, Bounds::exact(n)),
+            (Amount::AtLeast(n), _) => (
+                n,
+                Bounds {
+                    lo: n,
+                    hi: available.hi.map(|hi| hi.max(n)),
+                },
+            ),
+            (Amount::All, Part::Subset) | (Amount::Unknown, _) => (
+                0,
+                Bounds {
+                    lo: 0,
+                    hi: available.hi,
+                },
+            ),
+        }
+    }
+
+    fn apply(&self, env: &mut Env, world: &World) {
+        let source = self
+            .source()
+            .and_then(|l| Some((l, env.bounds(&self.from, l)?)));
+        let gained = match source {
+            Some((location, available)) => {
+                let (_, taken) = self.taken(available);
+                let left = if self.amount == Amount::All && self.from.part == Part::Whole {
+                    Bounds::exact(0)
+                } else {
+                    available.sub(taken.mul(self.spread()))
+                };
+                env.set(world, &self.from, location, left);
+
+                taken.mul(self.gather())
+            }
+            None => {
+                // Some cards from somewhere
+                for location in self.from.locations.iter() {
+                    if let Some(bounds) = env.locations.get_mut(location) {
+                        bounds.lo = 0;
+                    }
+                    env.current.remove(location);
+                }
+                Bounds::UNKNOWN
+            }
+        };
+
+        for location in self.to.locations.iter() {
+            if let Some(bounds) = env.bounds(&self.to, location) {
+                env.set(world, &self.to, location, bounds.add(gained));
+            }
+        }
+    }
+
+    fn check(&self, env: &Env, world: &World) -> Option<CardCountWarning> {
+        let location = self.source()?;
+        let available = env.bounds(&self.from, location)?;
+        let (needed, _) = self.taken(available);
+        let needed = needed.saturating_mul(self.spread().lo);
+
+        if needed == 0 {
+            return None;
+        }
+
+        match available.hi {
+            Some(hi) if hi < needed && world.single_instance(location) => {
+                Some(CardCountWarning::NotEnoughCards {
+                    location: location.clone(),
+                    needed,
+                    available: hi,
+                    span: self.span.clone(),
+                })
+            }
+            _ if available.lo < needed => Some(CardCountWarning::MaybeNotEnoughCards {
+                location: location.clone(),
+                needed,
+                span: self.span.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,383 @@
+///    Semantic diff between two versions of a game.
+///
+///    Compares what the game does and not how it is written:
+///    - SetUp-rules are compared as a multiset (reordering them is not a change).
+///      Combos, pointmaps and precedences are compared by name.
+///    - Stages are compared by name (on the lowered and formatted AST).
+///    - Transitions of the IR are compared per stage. A transition is identified
+///      by the paths of its States (see IrBuilder::state_path), so formatting
+///      and unrelated edits do not show up. SetUp-edges are left out, because
+///      they are already compared above (the game starts from "setup/exit",
+///      however many SetUp-rules there are).
+
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::ast as L;
+use crate::ast::ast_spanned::SGame;
+use crate::ir::{IrBuilder, LoweredPayLoad, Payload, SpannedPayload, StateID};
+use crate::lower::Lower;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<T> {
+    Added(T),
+    Removed(T),
+    Changed { old: T, new: T },
+}
+
+/// An edge of the IR, described by the paths of its States.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Transition {
+    pub from: String,
+    pub to: String,
+    pub label: String,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}: {}", self.from, self.to, self.label)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GameDiff {
+    pub setup: Vec<Change<String>>,
+    pub combos: Vec<Change<String>>,
+    pub pointmaps: Vec<Change<String>>,
+    pub precedences: Vec<Change<String>>,
+    pub stages: Vec<Change<String>>,
+    /// Stage name -> changed transitions ("game" for everything outside of a stage)
+    pub transitions: BTreeMap<String, Vec<Change<Transition>>>,
+}
+
+impl GameDiff {
+    pub fn is_empty(&self) -> bool {
+        self.setup.is_empty()
+            && self.combos.is_empty()
+            && self.pointmaps.is_empty()
+            && self.precedences.is_empty()
+            && self.stages.is_empty()
+            && self.transitions.is_empty()
+    }
+}
+
+pub fn diff_games(old: &SGame, new: &SGame) -> GameDiff {
+    let old_decls = Declarations::of(old);
+    let new_decls = Declarations::of(new);
+
+    let mut transitions = BTreeMap::new();
+    let old_transitions = transitions_per_stage(old);
+    let new_transitions = transitions_per_stage(new);
+    let mut stages: Vec<&String> = old_transitions
+        .keys()
+        .chain(new_transitions.keys())
+        .collect();
+    stages.sort();
+    stages.dedup();
+    for stage in stages {
+        let changes = diff_transitions(
+            old_transitions.get(stage).map_or(&[], |t| t.as_slice()),
+            new_transitions.get(stage).map_or(&[], |t| t.as_slice()),
+        );
+        if !changes.is_empty() {
+            transitions.insert(stage.clone(), changes);
+        }
+    }
+
+    GameDiff {
+        setup: diff_multiset(&old_decls.setup, &new_decls.setup),
+        combos: diff_named(&old_decls.combos, &new_decls.combos),
+        pointmaps: diff_named(&old_decls.pointmaps, &new_decls.pointmaps),
+        precedences: diff_named(&old_decls.precedences, &new_decls.precedences),
+        // Only the name, the transitions show what changed
+        stages: diff_named(&old_decls.stages, &new_decls.stages)
+            .into_iter()
+            .map(|change| match change {
+                Change::Added(stage) => Change::Added(stage_name(&stage)),
+                Change::Removed(stage) => Change::Removed(stage_name(&stage)),
+                Change::Changed { old, new } => Change::Changed {
+                    old: stage_name(&old),
+                    new: stage_name(&new),
+                },
+            })
+            .collect(),
+        transitions,
+    }
+}
+
+// ===========================================================================
+// Lowered AST
+// ===========================================================================
+/// Everything that is compared on the AST (formatted with fmt_ast).
+#[derive(Default)]
+struct Declarations {
+    setup: Vec<String>,
+    combos: BTreeMap<String, String>,
+    pointmaps: BTreeMap<String, String>,
+    precedences: BTreeMap<String, String>,
+    stages: BTreeMap<String, String>,
+}
+
+impl Declarations {
+    fn of(game: &SGame) -> Self {
+        let game: L::Game = game.lower();
+        let mut declarations = Declarations::default();
+        declarations.collect(&game.flows);
+
+        declarations
+    }
+
+    fn collect(&mut self, flows: &[L::FlowComponent]) {
+        for flow in flows.iter() {
+            match flow {
+                L::FlowComponent::GameRule {
+                    game_rule: L::GameRule::SetUp { setup },
+                } => {
+                    let text = setup.to_string();
+                    match setup {
+                        L::SetUpRule::CreateCombo { combo, .. } => {
+                            self.combos.insert(combo.clone(), text);
+                        }
+                        L::SetUpRule::CreatePointMap { pointmap, .. } => {
+                            self.pointmaps.insert(pointmap.clone(), text);
+                        }
+                        L::SetUpRule::CreatePrecedence { precedence, .. } => {
+                            self.precedences.insert(precedence.clone(), text);
+                        }
+                        _ => self.setup.push(text),
+                    }
+                }
+                L::FlowComponent::GameRule { .. } | L::FlowComponent::BettingRound { .. } => {}
+                L::FlowComponent::SeqStage { stage } => {
+                    self.stages.insert(stage.stage.clone(), stage.to_string());
+                    self.collect(&stage.flows);
+                }
+                L::FlowComponent::SimStage { stage } => {
+                    self.stages.insert(stage.stage.clone(), stage.to_string());
+                    self.collect(&stage.flows);
+                }
+                L::FlowComponent::IfRule { if_rule } => self.collect(&if_rule.flows),
+                L::FlowComponent::ChoiceRule { choice_rule } => self.collect(&choice_rule.options),
+                L::FlowComponent::OptionalRule { optional_rule } => {
+                    self.collect(&optional_rule.flows)
+                }
+                L::FlowComponent::TriggerRule { trigger_rule } => self.collect(&trigger_rule.flows),
+                L::FlowComponent::Conditional { conditional } => {
+                    for case in conditional.cases.iter() {
+                        match case {
+                            L::Case::NoBool { flows } => self.collect(flows),
+                            L::Case::Bool { flows, .. } => self.collect(flows),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// "stage Collect for current ..." -> "stage Collect"
+fn stage_name(stage: &str) -> String {
+    stage
+        .split_whitespace()
+        .take(2)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn diff_multiset(old: &[String], new: &[String]) -> Vec<Change<String>> {
+    let mut removed: Vec<String> = old.to_vec();
+    let mut added = Vec::new();
+    for item in new.iter() {
+        match removed.iter().position(|r| r == item) {
+            Some(i) => {
+                removed.remove(i);
+            }
+            None => added.push(item.clone()),
+        }
+    }
+
+    removed
+        .into_iter()
+        .map(Change::Removed)
+        .chain(added.into_iter().map(Change::Added))
+        .collect()
+}
+
+fn diff_named(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+) -> Vec<Change<String>> {
+    let mut changes = Vec::new();
+    for (name, old_text) in old.iter() {
+        match new.get(name) {
+            None => changes.push(Change::Removed(old_text.clone())),
+            Some(new_text) if new_text != old_text => changes.push(Change::Changed {
+                old: old_text.clone(),
+                new: new_text.clone(),
+            }),
+            _ => {}
+        }
+    }
+    for (name, new_text) in new.iter() {
+        if !old.contains_key(name) {
+            changes.push(Change::Added(new_text.clone()));
+        }
+    }
+
+    changes
+}
+
+// ===========================================================================
+// IR
+// ===========================================================================
+fn transitions_per_stage(game: &SGame) -> BTreeMap<String, Vec<Transition>> {
+    let mut builder: IrBuilder<SpannedPayload> = IrBuilder::default();
+    builder.build_ir(game);
+    builder.assign_stable_ids();
+
+    let path = |state: StateID| builder.state_path(state).unwrap_or("entry").to_string();
+
+    let mut transitions: BTreeMap<String, Vec<Transition>> = BTreeMap::new();
+    for (from, edges) in builder.fsm.states.iter() {
+        for edge in edges.iter() {
+            let payload: LoweredPayLoad = edge.payload.lower();
+            if let Payload::Action(L::GameRule::SetUp { .. }) = payload {
+                continue;
+            }
+
+            let from = path(*from);
+            // Innermost stage
+            let stage = from
+                .split('/')
+                .filter_map(|segment| segment.strip_prefix("stage:"))
+                .next_back()
+                .unwrap_or("game")
+                .to_string();
+
+            transitions.entry(stage).or_default().push(Transition {
+                from,
+                to: path(edge.to),
+                label: describe(&payload),
+            });
+        }
+    }
+    for stage_transitions in transitions.values_mut() {
+        stage_transitions.sort();
+    }
+
+    transitions
+}
+
+/// A transition is changed if there is a removed and an added one between the same States.
+fn diff_transitions(old: &[Transition], new: &[Transition]) -> Vec<Change<Transition>> {
+    let mut removed: Vec<Transition> = old.iter().filter(|t| !new.contains(t)).cloned().collect();
+    let mut added: Vec<Transition> = new.iter().filter(|t| !old.contains(t)).cloned().collect();
+
+    let mut changes = Vec::new();
+    removed.retain(|old| {
+        match added
+            .iter()
+            .position(|new| new.from == old.from && new.to == old.to)
+        {
+            Some(i) => {
+                changes.push(Change::Changed {
+                    old: old.clone(),
+                    new: added.remove(i),
+                });
+                false
+            }
+            None => true,
+        }
+    });
+
+    removed
+        .into_iter()
+        .map(Change::Removed)
+        .chain(added.into_iter().map(Change::Added))
+        .chain(changes)
+        .collect()
+}
+
+/// Payload::to_string only gives the kind of the edge, the diff needs the content.
+fn describe(payload: &LoweredPayLoad) -> String {
+    match payload {
+        Payload::Condition { expr, negated } => {
+            if *negated {
+                format!("not ({})", expr)
+            } else {
+                format!("{}", expr)
+            }
+        }
+        Payload::EndCondition {
+            expr,
+            negated,
+            stage,
+        } => {
+            if *negated {
+                format!("end {} {}", stage, expr)
+            } else {
+                format!("continue {} {}", stage, expr)
+            }
+        }
+        Payload::Action(rule) => format!("{}", rule),
+        Payload::StageRoundCounter(stage) => format!("next round of {}", stage),
+        Payload::EndStage(stage) => format!("end {}", stage),
+        Payload::Choice => String::from("choose"),
+        Payload::Optional => String::from("optional"),
+        Payload::Trigger {
+            players, claimed, ..
+        } => {
+            if *claimed {
+                format!("claim trigger among {}", players)
+            } else {
+                format!("trigger claimed by another of {}", players)
+            }
+        }
+        Payload::Bet { round, step } => format!("{} in {}", step, round),
+    }
+}
+
+// ===========================================================================
+// Output
+// ===========================================================================
+fn write_changes<T: fmt::Display>(
+    f: &mut fmt::Formatter<'_>,
+    title: &str,
+    changes: &[Change<T>],
+) -> fmt::Result {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(f, "{}:", title)?;
+    for change in changes.iter() {
+        match change {
+            Change::Added(new) => writeln!(f, "  + {}", new)?,
+            Change::Removed(old) => writeln!(f, "  - {}", old)?,
+            Change::Changed { old, new } => {
+                writeln!(f, "  ~ {}", old)?;
+                if old.to_string() != new.to_string() {
+                    writeln!(f, "    => {}", new)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for GameDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_changes(f, "setup", &self.setup)?;
+        write_changes(f, "combos", &self.combos)?;
+        write_changes(f, "pointmaps", &self.pointmaps)?;
+        write_changes(f, "precedences", &self.precedences)?;
+        write_changes(f, "stages", &self.stages)?;
+        for (stage, changes) in self.transitions.iter() {
+            write_changes(f, &format!("transitions of {}", stage), changes)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,718 @@
+///    Evaluation of FilterExpr and combos on concrete cards.
+///
+///    A FilterExpr describes a set of cards (e.g. `combo Sequence where
+///    ((size >= 3 and same Suite) and adjacent Rank using RankOrder)`):
+///    - size, same, distinct and adjacent look at the whole set,
+///    - higher/lower than and `Key is Value` have to hold for every card,
+///    - `follow Key is Value` holds if every card has the Value or none of the
+///      cards they are taken from (e.g. the Hand) has it (the player can not follow),
+///    - combos are evaluated by name (nested combos are allowed, cycles are an error),
+///    - and/or combine two filters.
+///
+///    matching_subsets enumerates every subset of a hand that matches a filter.
+///    The search is pruned with what every match has to fulfill (the `and`-parts):
+///    cards that do not fit the card filters are left out, `same` and `distinct`
+///    stop as soon as they are broken and `size` limits the depth.
+///
+///    partition splits a hand into non-overlapping combos that cover as much as
+///    possible. The rest is the deadwood of Rummy-style games.
+///
+///    playable and trick_winner are the two halves of a trick: the cards of a hand
+///    that follow the lead (if possible) and the card that takes the trick.
+///
+///    Everything that needs the state of a game (e.g. `Suite of top(Hand)`)
+///    can not be evaluated here and gives FilterError::NeedsGameState.
+
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+use crate::ast::*;
+use crate::tables::{PrecedenceTable, TableError, setup_rules, static_int};
+
+/// A card is a set of Key-Value pairs (e.g. Rank: Ace, Suite: Spades).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Card {
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Card {
+    pub fn new<'a>(attributes: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Card {
+            attributes: attributes
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(|value| value.as_str())
+    }
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let attributes: Vec<String> = self
+            .attributes
+            .iter()
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect();
+        write!(f, "({})", attributes.join(", "))
+    }
+}
+
+/// All cards of `card on Location: ...` (the cross product of every Types).
+pub fn cards_of(types: &[Types]) -> Vec<Card> {
+    let mut cards = Vec::new();
+    for t in types.iter() {
+        let mut product = vec![Card {
+            attributes: BTreeMap::new(),
+        }];
+        for (key, values) in t.types.iter() {
+            product = product
+                .into_iter()
+                .flat_map(|card| {
+                    values.iter().map(move |value| {
+                        let mut card = card.clone();
+                        card.attributes.insert(key.clone(), value.clone());
+                        card
+                    })
+                })
+                .collect();
+        }
+        cards.extend(product);
+    }
+
+    cards
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterError {
+    UnknownCombo(String),
+    /// The combo uses itself (directly or through other combos)
+    CyclicCombo(String),
+    /// A card does not have the Key
+    MissingKey {
+        key: String,
+        card: Card,
+    },
+    /// Unknown precedence, the card does not fit the precedence, ...
+    Table(TableError),
+    /// E.g. `Suite of top(Hand)` or `size >= &I:Memory`
+    NeedsGameState(String),
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::UnknownCombo(combo) => write!(f, "Unknown combo '{}'", combo),
+            FilterError::CyclicCombo(combo) => {
+                write!(f, "Combo '{}' is defined in terms of itself", combo)
+            }
+            FilterError::MissingKey { key, card } => {
+                write!(f, "Card {} does not have the key '{}'", card, key)
+            }
+            FilterError::Table(error) => write!(f, "{}", error),
+            FilterError::NeedsGameState(expr) => {
+                write!(f, "'{}' can only be evaluated in a running game", expr)
+            }
+        }
+    }
+}
+
+impl From<TableError> for FilterError {
+    fn from(error: TableError) -> Self {
+        match error {
+            TableError::NeedsGameState(expr) => FilterError::NeedsGameState(expr),
+            error => FilterError::Table(error),
+        }
+    }
+}
+
+/// Non-overlapping combos of a hand and the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partition {
+    /// Combo name -> indices of the cards
+    pub melds: Vec<(String, Vec<usize>)>,
+    pub rest: Vec<usize>,
+}
+
+/// Combos can be nested, but not deeper than this.
+const MAX_COMBO_DEPTH: usize = 32;
+
+/// The combos and precedences of a game.
+#[derive(Debug, Clone, Default)]
+pub struct FilterEvaluator {
+    combos: HashMap<String, FilterExpr>,
+    precedences: HashMap<String, PrecedenceTable>,
+}
+
+impl FilterEvaluator {
+    pub fn from_game(game: &Game) -> Self {
+        let mut evaluator = FilterEvaluator::default();
+        for setup in setup_rules(&game.flows) {
+            match setup {
+                SetUpRule::CreateCombo { combo, filter } => {
+                    evaluator.combos.insert(combo.clone(), filter.clone());
+                }
+                SetUpRule::CreatePrecedence { precedence, kvs } => {
+                    evaluator
+                        .precedences
+                        .insert(precedence.clone(), PrecedenceTable::new(precedence, kvs));
+                }
+                _ => {}
+            }
+        }
+
+        evaluator
+    }
+
+    /// Lets `adjacent ... using Precedence` wrap around (e.g. King, Ace, Two).
+    pub fn set_wrap_around(
+        &mut self,
+        precedence: &str,
+        wrap_around: bool,
+    ) -> Result<(), FilterError> {
+        self.precedences
+            .get_mut(precedence)
+            .ok_or_else(|| TableError::UnknownTable(precedence.to_string()))?
+            .wrap_around = wrap_around;
+
+        Ok(())
+    }
+
+    fn precedence(&self, precedence: &str) -> Result<&PrecedenceTable, FilterError> {
+        Ok(self
+            .precedences
+            .get(precedence)
+            .ok_or_else(|| TableError::UnknownTable(precedence.to_string()))?)
+    }
+
+    pub fn combo(&self, combo: &str) -> Result<&FilterExpr, FilterError> {
+        self.combos
+            .get(combo)
+            .ok_or_else(|| FilterError::UnknownCombo(combo.to_string()))
+    }
+
+    /// Does the set of cards match the filter? The cards are all there is to follow from.
+    pub fn matches(&self, filter: &FilterExpr, cards: &[&Card]) -> Result<bool, FilterError> {
+        self.matches_at(filter, cards, cards, 0)
+    }
+
+    /// Does the set of cards, taken from `source` (e.g. the Hand), match the filter?
+    pub fn matches_from(
+        &self,
+        filter: &FilterExpr,
+        cards: &[&Card],
+        source: &[&Card],
+    ) -> Result<bool, FilterError> {
+        self.matches_at(filter, cards, source, 0)
+    }
+
+    pub fn matches_combo(&self, combo: &str, cards: &[&Card]) -> Result<bool, FilterError> {
+        self.matches(self.combo(combo)?, cards)
+    }
+
+    fn matches_at(
+        &self,
+        filter: &FilterExpr,
+        cards: &[&Card],
+        source: &[&Card],
+        depth: usize,
+    ) -> Result<bool, FilterError> {
+        match filter {
+            FilterExpr::Aggregate { aggregate } => {
+                self.aggregate_matches(aggregate, cards, source, depth)
+            }
+            FilterExpr::Binary {
+                filter,
+                op,
+                filter1,
+            } => {
+                let left = self.matches_at(filter, cards, source, depth)?;
+                match op {
+                    FilterOp::And => Ok(left && self.matches_at(filter1, cards, source, depth)?),
+                    FilterOp::Or => Ok(left || self.matches_at(filter1, cards, source, depth)?),
+                }
+            }
+        }
+    }
+
+    fn aggregate_matches(
+        &self,
+        aggregate: &AggregateFilter,
+        cards: &[&Card],
+        source: &[&Card],
+        depth: usize,
+    ) -> Result<bool, FilterError> {
+        match aggregate {
+            AggregateFilter::Size { cmp, int_expr } => {
+                Ok(compare(cards.len() as i64, cmp, static_int(int_expr)?))
+            }
+            AggregateFilter::Same { key } => {
+                let values = values(key, cards)?;
+                Ok(values.windows(2).all(|w| w[0] == w[1]))
+            }
+            AggregateFilter::Distinct { key } => {
+                let mut values = values(key, cards)?;
+                values.sort();
+                Ok(values.windows(2).all(|w| w[0] != w[1]))
+            }
+            AggregateFilter::Adjacent { key, precedence } => {
+                Ok(self.precedence(precedence)?.adjacent(key, cards)?)
+            }
+            AggregateFilter::Combo { combo } => self.combo_matches(combo, cards, source, depth),
+            AggregateFilter::NotCombo { combo } => {
+                Ok(!self.combo_matches(combo, cards, source, depth)?)
+            }
+            AggregateFilter::FollowKey { key, string } => {
+                // Only a player that can not follow may play other cards
+                let lead = static_string(string)?;
+                if values(key, source)?.iter().all(|value| *value != lead) {
+                    return Ok(true);
+                }
+                Ok(values(key, cards)?.iter().all(|value| *value == lead))
+            }
+            // Card filters
+            _ => {
+                for card in cards.iter() {
+                    if !self.card_matches(aggregate, card)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn combo_matches(
+        &self,
+        combo: &str,
+        cards: &[&Card],
+        source: &[&Card],
+        depth: usize,
+    ) -> Result<bool, FilterError> {
+        if depth >= MAX_COMBO_DEPTH {
+            return Err(FilterError::CyclicCombo(combo.to_string()));
+        }
+
+        self.matches_at(self.combo(combo)?, cards, source, depth + 1)
+    }
+
+    /// Filters that have to hold for every single card (true for all others).
+    fn card_matches(&self, aggregate: &AggregateFilter, card: &Card) -> Result<bool, FilterError> {
+        match aggregate {
+            AggregateFilter::Higher {
+                key,
+                value,
+                precedence,
+            } => Ok(self
+                .precedence(precedence)?
+                .higher_than(card, key, &static_string(value)?)?),
+            AggregateFilter::Lower {
+                key,
+                value,
+                precedence,
+            } => Ok(self
+                .precedence(precedence)?
+                .lower_than(card, key, &static_string(value)?)?),
+            AggregateFilter::KeyIsString { key, string } => {
+                Ok(value(key, card)? == static_string(string)?)
+            }
+            AggregateFilter::KeyIsNotString { key, string } => {
+                Ok(value(key, card)? != static_string(string)?)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    // =======================================================================
+    // Enumeration
+    // =======================================================================
+    /// All non-empty subsets of `cards` that match the filter (as sorted indices).
+    pub fn matching_subsets(
+        &self,
+        filter: &FilterExpr,
+        cards: &[Card],
+    ) -> Result<Vec<Vec<usize>>, FilterError> {
+        // An `or` has no common constraints, so both sides are searched on their own
+        if let FilterExpr::Binary {
+            filter: left,
+            op: FilterOp::Or,
+            filter1: right,
+        } = filter
+        {
+            let mut subsets = self.matching_subsets(left, cards)?;
+            subsets.extend(self.matching_subsets(right, cards)?);
+            subsets.sort();
+            subsets.dedup();
+            return Ok(subsets);
+        }
+
+        let mut pruning = Pruning::default();
+        self.pruning(filter, &mut pruning, 0)?;
+
+        let mut candidates = Vec::new();
+        for (index, card) in cards.iter().enumerate() {
+            let mut fits = true;
+            for aggregate in pruning.card_filters.iter() {
+                fits &= self.card_matches(aggregate, card)?;
+            }
+            if fits {
+                candidates.push(index);
+            }
+        }
+
+        let source: Vec<&Card> = cards.iter().collect();
+        let mut search = Search {
+            evaluator: self,
+            filter,
+            cards,
+            source: &source,
+            candidates: &candidates,
+            pruning: &pruning,
+            current: Vec::new(),
+            found: Vec::new(),
+        };
+        search.extend(0)?;
+
+        Ok(search.found)
+    }
+
+    /// Collects the constraints of the `and`-parts of the filter.
+    fn pruning<'a>(
+        &'a self,
+        filter: &'a FilterExpr,
+        pruning: &mut Pruning<'a>,
+        depth: usize,
+    ) -> Result<(), FilterError> {
+        let aggregate = match filter {
+            FilterExpr::Binary {
+                filter,
+                op: FilterOp::And,
+                filter1,
+            } => {
+                self.pruning(filter, pruning, depth)?;
+                return self.pruning(filter1, pruning, depth);
+            }
+            FilterExpr::Binary { .. } => return Ok(()),
+            FilterExpr::Aggregate { aggregate } => aggregate,
+        };
+
+        match aggregate {
+            AggregateFilter::Size { cmp, int_expr } => {
+                let n = static_int(int_expr)?.max(0) as usize;
+                let (min, max) = match cmp {
+                    IntCompare::Eq => (n, Some(n)),
+                    IntCompare::Ge => (n, None),
+                    IntCompare::Gt => (n + 1, None),
+                    IntCompare::Le => (0, Some(n)),
+                    IntCompare::Lt => (0, Some(n.saturating_sub(1))),
+                    IntCompare::Neq => (0, None),
+                };
+                pruning.min_size = pruning.min_size.max(min);
+                pruning.max_size = match (pruning.max_size, max) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+            AggregateFilter::Same { key } => pruning.same.push(key),
+            AggregateFilter::Distinct { key } => pruning.distinct.push(key),
+            AggregateFilter::Combo { combo } => {
+                if depth >= MAX_COMBO_DEPTH {
+                    return Err(FilterError::CyclicCombo(combo.to_string()));
+                }
+                self.pruning(self.combo(combo)?, pruning, depth + 1)?;
+            }
+            AggregateFilter::Higher { .. }
+            | AggregateFilter::Lower { .. }
+            | AggregateFilter::KeyIsString { .. }
+            | AggregateFilter::KeyIsNotString { .. } => pruning.card_filters.push(aggregate),
+            AggregateFilter::Adjacent { .. }
+            | AggregateFilter::NotCombo { .. }
+            | AggregateFilter::FollowKey { .. } => {}
+        }
+
+        Ok(())
+    }
+
+    // =======================================================================
+    // Partition
+    // =======================================================================
+    /// Non-overlapping combos that cover as many cards as possible.
+    pub fn partition(&self, cards: &[Card], combos: &[&str]) -> Result<Partition, FilterError> {
+        self.partition_by(cards, combos, |_| 1)
+    }
+
+    /// Non-overlapping combos that cover as much weight as possible
+    /// (e.g. the points of the cards, so the rest is the smallest deadwood).
+    /// On a tie the combos come first in the order they are given.
+    pub fn partition_by<W>(
+        &self,
+        cards: &[Card],
+        combos: &[&str],
+        weight: W,
+    ) -> Result<Partition, FilterError>
+    where
+        W: Fn(&Card) -> i64,
+    {
+        // Meld candidates by their smallest card
+        let mut melds_of: Vec<Vec<(usize, Vec<usize>)>> = vec![Vec::new(); cards.len()];
+        for (combo_index, combo) in combos.iter().enumerate() {
+            for subset in self.matching_subsets(self.combo(combo)?, cards)? {
+                melds_of[subset[0]].push((combo_index, subset));
+            }
+        }
+
+        let weights: Vec<i64> = cards.iter().map(|card| weight(card).max(0)).collect();
+        let mut cover = Cover {
+            melds_of: &melds_of,
+            weights: &weights,
+            used: vec![false; cards.len()],
+            current: Vec::new(),
+            best: Vec::new(),
+            best_weight: -1,
+        };
+        cover.search(0, 0);
+
+        let mut used = vec![false; cards.len()];
+        let melds = cover
+            .best
+            .iter()
+            .map(|(combo_index, subset)| {
+                for index in subset.iter() {
+                    used[*index] = true;
+                }
+                (combos[*combo_index].to_string(), subset.clone())
+            })
+            .collect();
+
+        Ok(Partition {
+            melds,
+            rest: (0..cards.len()).filter(|index| !used[*index]).collect(),
+        })
+    }
+
+    // =======================================================================
+    // Tricks
+    // =======================================================================
+    /// The cards of `hand` that may be played: the ones with the lead Value of the Key
+    /// if there are any (follow suit), otherwise all of them.
+    pub fn playable(
+        &self,
+        hand: &[Card],
+        key: &str,
+        lead: &str,
+    ) -> Result<Vec<usize>, FilterError> {
+        let mut follows = Vec::new();
+        for (index, card) in hand.iter().enumerate() {
+            if value(key, card)? == lead {
+                follows.push(index);
+            }
+        }
+
+        if follows.is_empty() {
+            return Ok((0..hand.len()).collect());
+        }
+        Ok(follows)
+    }
+
+    /// The card that takes the trick: the highest trump if there is one, otherwise
+    /// the highest card that follows the lead. On a tie the first card wins.
+    /// None if no card can win (e.g. an empty trick).
+    pub fn trick_winner(
+        &self,
+        trick: &[Card],
+        precedence: &str,
+        key: &str,
+        lead: &str,
+        trump: Option<&str>,
+    ) -> Result<Option<usize>, FilterError> {
+        let precedence = self.precedence(precedence)?;
+
+        let mut winner: Option<(usize, bool)> = None;
+        for (index, card) in trick.iter().enumerate() {
+            let value = value(key, card)?;
+            let is_trump = Some(value) == trump;
+            if !is_trump && value != lead {
+                continue;
+            }
+
+            let better = match winner {
+                None => true,
+                // A trump beats every card that follows the lead
+                Some((_, best_is_trump)) if best_is_trump != is_trump => is_trump,
+                Some((best, _)) => precedence.compare(card, &trick[best])? == Ordering::Greater,
+            };
+            if better {
+                winner = Some((index, is_trump));
+            }
+        }
+
+        Ok(winner.map(|(index, _)| index))
+    }
+}
+
+fn value<'a>(key: &str, card: &'a Card) -> Result<&'a str, FilterError> {
+    card.get(key).ok_or_else(|| FilterError::MissingKey {
+        key: key.to_string(),
+        card: card.clone(),
+    })
+}
+
+fn values<'a>(key: &str, cards: &[&'a Card]) -> Result<Vec<&'a str>, FilterError> {
+    cards.iter().map(|card| value(key, card)).collect()
+}
+
+fn compare(left: i64, cmp: &IntCompare, right: i64) -> bool {
+    match cmp {
+        IntCompare::Eq => left == right,
+        IntCompare::Neq => left != right,
+        IntCompare::Gt => left > right,
+        IntCompare::Lt => left < right,
+        IntCompare::Ge => left >= right,
+        IntCompare::Le => left <= right,
+    }
+}
+
+fn static_string(string: &StringExpr) -> Result<String, FilterError> {
+    match string {
+        StringExpr::Literal { value } => Ok(value.clone()),
+        _ => Err(FilterError::NeedsGameState(string.to_string())),
+    }
+}
+
+/// What every match of a filter has to fulfill.
+#[derive(Default)]
+struct Pruning<'a> {
+    min_size: usize,
+    max_size: Option<usize>,
+    same: Vec<&'a String>,
+    distinct: Vec<&'a String>,
+    card_filters: Vec<&'a AggregateFilter>,
+}
+
+/// Backtracking over the candidates in index order.
+struct Search<'a> {
+    evaluator: &'a FilterEvaluator,
+    filter: &'a FilterExpr,
+    cards: &'a [Card],
+    source: &'a [&'a Card],
+    candidates: &'a [usize],
+    pruning: &'a Pruning<'a>,
+    current: Vec<usize>,
+    found: Vec<Vec<usize>>,
+}
+
+impl Search<'_> {
+    fn extend(&mut self, from: usize) -> Result<(), FilterError> {
+        for i in from..self.candidates.len() {
+            // Not enough cards left for the minimum size
+            if self.current.len() + self.candidates.len() - i < self.pruning.min_size {
+                break;
+            }
+
+            let index = self.candidates[i];
+            if !self.fits(index)? {
+                continue;
+            }
+
+            self.current.push(index);
+            let cards: Vec<&Card> = self.current.iter().map(|i| &self.cards[*i]).collect();
+            if self.current.len() >= self.pruning.min_size
+                && self
+                    .evaluator
+                    .matches_from(self.filter, &cards, self.source)?
+            {
+                self.found.push(self.current.clone());
+            }
+            if self
+                .pruning
+                .max_size
+                .is_none_or(|max| self.current.len() < max)
+            {
+                self.extend(i + 1)?;
+            }
+            self.current.pop();
+        }
+
+        Ok(())
+    }
+
+    /// `same` and `distinct` still hold with the card (they can only break by adding cards).
+    fn fits(&self, index: usize) -> Result<bool, FilterError> {
+        let card = &self.cards[index];
+        for key in self.pruning.same.iter() {
+            if let Some(first) = self.current.first()
+                && value(key, &self.cards[*first])? != value(key, card)?
+            {
+                return Ok(false);
+            }
+        }
+        for key in self.pruning.distinct.iter() {
+            for other in self.current.iter() {
+                if value(key, &self.cards[*other])? == value(key, card)? {
+                    return Ok(false);
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Branch and bound for the partition.
+struct Cover<'a> {
+    /// Card -> melds whose smallest card it is
+    melds_of: &'a [Vec<(usize, Vec<usize>)>],
+    weights: &'a [i64],
+    used: Vec<bool>,
+    current: Vec<(usize, Vec<usize>)>,
+    best: Vec<(usize, Vec<usize>)>,
+    best_weight: i64,
+}
+
+impl Cover<'_> {
+    fn search(&mut self, from: usize, covered: i64) {
+        // Everything that is left can not beat the best
+        let left: i64 = (from..self.weights.len())
+            .filter(|i| !self.used[*i])
+            .map(|i| self.weights[i])
+            .sum();
+        if covered + left <= self.best_weight {
+            return;
+        }
+
+        let Some(card) = (from..self.weights.len()).find(|i| !self.used[*i]) else {
+            self.best_weight = covered;
+            self.best = self.current.clone();
+            return;
+        };
+
+        for (combo, meld) in self.melds_of[card].iter() {
+            if meld.iter().any(|i| self.used[*i]) {
+                continue;
+            }
+
+            let weight: i64 = meld.iter().map(|i| self.weights[*i]).sum();
+            for i in meld.iter() {
+                self.used[*i] = true;
+            }
+            self.current.push((*combo, meld.clone()));
+
+            self.search(card + 1, covered + weight);
+
+            self.current.pop();
+            for i in meld.iter() {
+                self.used[*i] = false;
+            }
+        }
+
+        // The card stays in the rest
+        self.used[card] = true;
+        self.search(card + 1, covered);
+        self.used[card] = false;
+    }
+}
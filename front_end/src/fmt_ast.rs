@@ -184,6 +184,16 @@ impl fmt::Display for Players {
     }
 }
 
+impl fmt::Display for Teams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Teams::Team { team } => &format!("{}", team),
+            Teams::TeamCollection { team_collection } => &format!("{}", team_collection),
+        };
+        f.write_str(s)
+    }
+}
+
 impl fmt::Display for EndType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -191,6 +201,7 @@ impl fmt::Display for EndType {
             EndType::CurrentStage => "stage",
             EndType::Stage { stage } => &format!("{}", stage),
             EndType::GameWithWinner { players } => &format!("game with winner {}", players),
+            EndType::GameWithTeamWinner { teams } => &format!("game with winner {}", teams),
         };
         f.write_str(s)
     }
@@ -354,6 +365,9 @@ impl fmt::Display for AggregateInt {
                 extrema,
                 int_collection,
             } => &format!("{}({})", extrema, int_collection),
+            AggregateInt::TokenCount { token, token_loc } => {
+                &format!("tokens {} in {}", token, token_loc)
+            }
         };
         f.write_str(s)
     }
@@ -622,6 +636,25 @@ impl fmt::Display for AggregateCardPosition {
                 card_set,
                 precedence,
             } => &format!("{} of {} using {}", extrema, card_set, precedence),
+            AggregateCardPosition::TrickWinner {
+                card_set,
+                precedence,
+                key,
+                lead,
+            } => &format!(
+                "winner of {} using {} follow {} is {}",
+                card_set, precedence, key, lead
+            ),
+            AggregateCardPosition::TrickWinnerTrump {
+                card_set,
+                precedence,
+                trump,
+                key,
+                lead,
+            } => &format!(
+                "winner of {} using {} trump {} follow {} is {}",
+                card_set, precedence, trump, key, lead
+            ),
         };
         f.write_str(s)
     }
@@ -705,6 +738,10 @@ impl fmt::Display for AggregateFilter {
                 key,
                 string: string_expr,
             } => &format!("{} is not {}", key, string_expr),
+            AggregateFilter::FollowKey {
+                key,
+                string: string_expr,
+            } => &format!("follow {} is {}", key, string_expr),
             AggregateFilter::Combo { combo } => &format!("{}", combo),
             AggregateFilter::NotCombo { combo } => &format!("not {}", combo),
         };
@@ -1203,6 +1240,15 @@ impl fmt::Display for ScoreRule {
                 memory,
                 players,
             } => &format!("score {} to {} of {}", int_expr, memory, players),
+            ScoreRule::ScoreTeams {
+                int: int_expr,
+                teams,
+            } => &format!("score {} to {}", int_expr, teams),
+            ScoreRule::ScoreMemoryTeams {
+                int: int_expr,
+                memory,
+                teams,
+            } => &format!("score {} to {} of {}", int_expr, memory, teams),
         };
         f.write_str(s)
     }
@@ -1216,6 +1262,11 @@ impl fmt::Display for WinnerRule {
                 extrema,
                 winner_type,
             } => &format!("winner is {} {}", extrema, winner_type),
+            WinnerRule::WinnerTeams { teams } => &format!("winner is {}", teams),
+            WinnerRule::WinnerTeamWith {
+                extrema,
+                winner_type,
+            } => &format!("winner is {} team {}", extrema, winner_type),
         };
         f.write_str(s)
     }
@@ -1333,6 +1384,30 @@ impl fmt::Display for TriggerRule {
     }
 }
 
+impl fmt::Display for BettingRound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            BettingRound::Bet { players, stake } => &format!("betting for {} {}", players, stake),
+            BettingRound::BetMinimum {
+                players,
+                stake,
+                minimum,
+            } => &format!("betting for {} {} minimum {}", players, stake, minimum),
+        };
+        f.write_str(s)
+    }
+}
+
+impl fmt::Display for Stake {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Stake::Tokens { token, from, to } => &format!("with {} from {} to {}", token, from, to),
+            Stake::Memory { memory } => &format!("on {}", memory),
+        };
+        f.write_str(s)
+    }
+}
+
 impl fmt::Display for Case {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
@@ -1393,6 +1468,7 @@ impl fmt::Display for FlowComponent {
             FlowComponent::OptionalRule { optional_rule } => &format!("{}", optional_rule),
             FlowComponent::Conditional { conditional } => &format!("{}", conditional),
             FlowComponent::TriggerRule { trigger_rule } => &format!("{}", trigger_rule),
+            FlowComponent::BettingRound { betting_round } => &format!("{}", betting_round),
         };
         f.write_str(s)
     }
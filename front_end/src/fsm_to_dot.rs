@@ -124,14 +124,17 @@ where
     writeln!(file, "  entry [shape=point];")?;
     writeln!(file, "  entry -> {:?};", fsm.entry.raw())?;
 
+    // The id of an edge is its EdgeID (stable, see IrBuilder::assign_stable_ids)
     for (state_id, edges) in &fsm.states {
-        for edge in edges.iter() {
+        for (index, edge) in edges.iter().enumerate() {
             let label = edge.payload.to_string().replace('"', "\\\"");
             writeln!(
                 file,
-                "  {:?} -> {:?} [label=\" {} \"];",
+                "  {:?} -> {:?} [id=\"e{}_{}\", label=\" {} \"];",
                 state_id.raw(),
                 edge.to.raw(),
+                state_id.raw(),
+                index,
                 label
             )?;
         }
@@ -0,0 +1,498 @@
+///    Information-flow analysis for private and face-down cards.
+///
+///    Every Location gets the set of Statuses its cards can have (dataflow over the IR):
+///    - `card on Stock: ...` creates face down cards,
+///    - moves and deals put cards with their Status on the target,
+///    - FlipAction changes the Status of the cards.
+///
+///    With that two kinds of leaks are reported:
+///    - Revealed: private cards of another player or on the table are flipped or
+///      moved `face up` (e.g. `flip Hand of next to face up`).
+///    - HiddenCondition: a Condition reads face down cards or private cards
+///      that do not belong to the current player. Which branch is taken can be
+///      seen by the current player, so the Condition tells them something about
+///      the hidden cards.
+///      Only looking at the size of a Location (`Hand of next empty`,
+///      `size of Hand of next`) does not leak anything.
+///
+///    Private cards on the table are known by exactly one player, but it is not
+///    known by whom, so they are treated like the private cards of another player.
+
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::ast::ast_spanned::*;
+use crate::ir::{Ir, Payload, SpannedPayload, StateID};
+use crate::spans::OwnedSpan;
+use crate::walker::{AstPass, Walker};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InformationLeak {
+    /// Private cards of another player are turned face up.
+    Revealed { location: String, span: OwnedSpan },
+    /// The outcome of a Condition depends on cards the current player can not see.
+    HiddenCondition { location: String, span: OwnedSpan },
+}
+
+impl fmt::Display for InformationLeak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InformationLeak::Revealed { location, .. } => write!(
+                f,
+                "The private cards on '{}' of another player are turned face up",
+                location
+            ),
+            InformationLeak::HiddenCondition { location, .. } => write!(
+                f,
+                "The condition depends on hidden cards on '{}', but the current player can see its outcome",
+                location
+            ),
+        }
+    }
+}
+
+impl InformationLeak {
+    pub fn span(&self) -> &OwnedSpan {
+        match self {
+            InformationLeak::Revealed { span, .. } => span,
+            InformationLeak::HiddenCondition { span, .. } => span,
+        }
+    }
+}
+
+pub fn information_flow_analysis(fsm: &Ir<SpannedPayload>) -> Vec<InformationLeak> {
+    let table = table_locations(fsm);
+    let states = fixpoint(fsm, &table);
+
+    let mut leaks = Vec::new();
+    for (from, edges) in fsm.states.iter() {
+        let Some(env) = states.get(from) else {
+            continue;
+        };
+
+        for edge in edges.iter() {
+            let found = match &edge.payload {
+                Payload::Condition { expr, .. } => hidden_condition(expr, env, &table),
+                Payload::EndCondition { expr, .. } => match &expr.node {
+                    EndCondition::UntilBool { bool_expr }
+                    | EndCondition::UntilBoolRep { bool_expr, .. } => {
+                        hidden_condition(bool_expr, env, &table)
+                    }
+                    _ => vec![],
+                },
+                Payload::Action(rule) => match &rule.node {
+                    GameRule::Action { action } => revealed(action, env, &table),
+                    _ => vec![],
+                },
+                _ => vec![],
+            };
+
+            for leak in found {
+                if !leaks.contains(&leak) {
+                    leaks.push(leak);
+                }
+            }
+        }
+    }
+
+    leaks
+}
+
+// ===========================================================================
+// Domain
+// ===========================================================================
+/// The Statuses the cards on a Location can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Statuses {
+    face_up: bool,
+    face_down: bool,
+    private: bool,
+}
+
+impl Statuses {
+    fn of(status: &Status) -> Self {
+        Statuses {
+            face_up: matches!(status, Status::FaceUp),
+            face_down: matches!(status, Status::FaceDown),
+            private: matches!(status, Status::Private),
+        }
+    }
+
+    fn union(self, other: Statuses) -> Self {
+        Statuses {
+            face_up: self.face_up || other.face_up,
+            face_down: self.face_down || other.face_down,
+            private: self.private || other.private,
+        }
+    }
+}
+
+/// Statuses on every Location
+type Env = BTreeMap<String, Statuses>;
+
+fn join_env(a: &Env, b: &Env) -> Env {
+    let mut joined = a.clone();
+    for (location, statuses) in b.iter() {
+        let entry = joined.entry(location.clone()).or_default();
+        *entry = entry.union(*statuses);
+    }
+
+    joined
+}
+
+/// Locations that are created `on table`.
+fn table_locations(fsm: &Ir<SpannedPayload>) -> HashSet<String> {
+    let mut table = HashSet::new();
+    for edge in fsm.states.values().flatten() {
+        if let Payload::Action(rule) = &edge.payload
+            && let GameRule::SetUp { setup } = &rule.node
+            && let SetUpRule::CreateLocation { locations, owner } = &setup.node
+            && let Owner::Table = owner.node
+        {
+            table.extend(locations.iter().map(|l| l.node.clone()));
+        }
+    }
+
+    table
+}
+
+// ===========================================================================
+// Fixpoint
+// ===========================================================================
+/// The set of Statuses only grows, so there is no widening needed.
+fn fixpoint(fsm: &Ir<SpannedPayload>, table: &HashSet<String>) -> HashMap<StateID, Env> {
+    let mut states: HashMap<StateID, Env> = HashMap::from([(fsm.entry, Env::new())]);
+
+    let mut worklist = VecDeque::from([fsm.entry]);
+    while let Some(state) = worklist.pop_front() {
+        let env = states[&state].clone();
+
+        for edge in fsm.states.get(&state).into_iter().flatten() {
+            let next = transfer(&edge.payload, &env, table);
+            let joined = match states.get(&edge.to) {
+                Some(old) => join_env(old, &next),
+                None => next,
+            };
+
+            if states.get(&edge.to) != Some(&joined) {
+                states.insert(edge.to, joined);
+                worklist.push_back(edge.to);
+            }
+        }
+    }
+
+    states
+}
+
+fn transfer(payload: &SpannedPayload, env: &Env, table: &HashSet<String>) -> Env {
+    let mut env = env.clone();
+    let Payload::Action(rule) = payload else {
+        return env;
+    };
+
+    match &rule.node {
+        GameRule::SetUp { setup } => {
+            if let SetUpRule::CreateCardOnLocation { location, .. } = &setup.node {
+                let entry = env.entry(location.node.clone()).or_default();
+                entry.face_down = true;
+            }
+        }
+        GameRule::Action { action } => match &action.node {
+            ActionRule::FlipAction { card_set, status } => {
+                let side = Side::of(&card_set.node, table);
+                for location in side.locations.iter() {
+                    let entry = env.entry(location.clone()).or_default();
+                    *entry = if side.whole {
+                        Statuses::of(&status.node)
+                    } else {
+                        entry.union(Statuses::of(&status.node))
+                    };
+                }
+            }
+            ActionRule::Move { move_type } => {
+                if let Some((from, status, to, all)) = move_card_set(move_type) {
+                    let from = Side::of(&from.node, table);
+                    if all && from.whole {
+                        for location in from.locations.iter() {
+                            env.insert(location.clone(), Statuses::default());
+                        }
+                    }
+                    for location in Side::of(&to.node, table).locations.iter() {
+                        let entry = env.entry(location.clone()).or_default();
+                        *entry = entry.union(Statuses::of(&status.node));
+                    }
+                }
+            }
+            _ => {}
+        },
+        GameRule::Scoring { .. } => {}
+    }
+
+    env
+}
+
+// ===========================================================================
+// CardSets
+// ===========================================================================
+/// Who owns the cards that are looked at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Whose {
+    Current,
+    /// Any other player or multiple players (e.g. `all`)
+    Other,
+    Table,
+}
+
+/// The Locations of a CardSet and who owns them.
+#[derive(Debug, Clone)]
+struct Side {
+    /// Empty if the Location is not known (Memory)
+    locations: Vec<String>,
+    whose: Whose,
+    /// The whole Location (no filter, combo or position)
+    whole: bool,
+}
+
+impl Side {
+    fn of(card_set: &CardSet, table: &HashSet<String>) -> Self {
+        let (group, whose) = match card_set {
+            CardSet::Group { group } => (group, Whose::Current),
+            CardSet::GroupOwner { group, owner } => (group, whose(&owner.node)),
+            CardSet::Memory { .. } => {
+                return Side {
+                    locations: vec![],
+                    whose: Whose::Other,
+                    whole: false,
+                };
+            }
+        };
+
+        let (locations, whole) = match &group.node {
+            Group::Groupable { groupable } => (groupable_locations(groupable), true),
+            Group::Where { groupable, .. }
+            | Group::NotCombo { groupable, .. }
+            | Group::Combo { groupable, .. } => (groupable_locations(groupable), false),
+            Group::CardPosition { card_position } => {
+                (card_position_locations(&card_position.node, table), false)
+            }
+        };
+
+        Side {
+            whose: match &locations[..] {
+                [location] if table.contains(location) => Whose::Table,
+                _ => whose,
+            },
+            locations,
+            whole,
+        }
+    }
+
+    /// Cards on the Locations the current player can not see.
+    fn hidden(&self, env: &Env) -> Option<&String> {
+        self.locations.iter().find(|location| {
+            env.get(*location).is_some_and(|statuses| {
+                statuses.face_down || (statuses.private && self.whose != Whose::Current)
+            })
+        })
+    }
+}
+
+fn whose(owner: &Owner) -> Whose {
+    match owner {
+        Owner::Player { player } => match &player.node {
+            PlayerExpr::Runtime { runtime } if matches!(runtime.node, RuntimePlayer::Current) => {
+                Whose::Current
+            }
+            _ => Whose::Other,
+        },
+        Owner::Table => Whose::Table,
+        _ => Whose::Other,
+    }
+}
+
+fn groupable_locations(groupable: &SGroupable) -> Vec<String> {
+    match &groupable.node {
+        Groupable::Location { name } => vec![name.node.clone()],
+        Groupable::LocationCollection {
+            location_collection,
+        } => match &location_collection.node {
+            LocationCollection::Literal { locations } => {
+                locations.iter().map(|l| l.node.clone()).collect()
+            }
+            LocationCollection::Memory { .. } => vec![],
+        },
+    }
+}
+
+fn card_position_locations(card_position: &CardPosition, table: &HashSet<String>) -> Vec<String> {
+    match card_position {
+        CardPosition::Query { query } => match &query.node {
+            QueryCardPosition::At { location, .. }
+            | QueryCardPosition::Top { location }
+            | QueryCardPosition::Bottom { location } => vec![location.node.clone()],
+        },
+        CardPosition::Aggregate { aggregate } => match &aggregate.node {
+            AggregateCardPosition::ExtremaPointMap { card_set, .. }
+            | AggregateCardPosition::ExtremaPrecedence { card_set, .. }
+            | AggregateCardPosition::TrickWinner { card_set, .. }
+            | AggregateCardPosition::TrickWinnerTrump { card_set, .. } => {
+                Side::of(&card_set.node, table).locations
+            }
+        },
+    }
+}
+
+/// From, Status and To of a move, deal or exchange of cards
+/// and if all cards of From are moved.
+fn move_card_set(move_type: &SMoveType) -> Option<(&SCardSet, &SStatus, &SCardSet, bool)> {
+    let move_cs = match &move_type.node {
+        MoveType::Deal { deal } => match &deal.node {
+            DealMove::MoveCardSet { deal_cs } => deal_cs,
+        },
+        MoveType::Exchange { exchange } => match &exchange.node {
+            ExchangeMove::MoveCardSet { exchange_cs } => exchange_cs,
+        },
+        MoveType::Classic { classic } => match &classic.node {
+            ClassicMove::MoveCardSet { move_cs } => move_cs,
+        },
+        // Tokens are not cards
+        MoveType::Place { .. } => return None,
+    };
+
+    match &move_cs.node {
+        MoveCardSet::Move { from, status, to } => Some((from, status, to, true)),
+        MoveCardSet::MoveQuantity {
+            quantity,
+            from,
+            status,
+            to,
+        } => {
+            let all = matches!(
+                &quantity.node,
+                Quantity::Quantifier { quantifier } if matches!(quantifier.node, Quantifier::All)
+            );
+            Some((from, status, to, all))
+        }
+    }
+}
+
+// ===========================================================================
+// Leaks
+// ===========================================================================
+fn revealed(action: &SActionRule, env: &Env, table: &HashSet<String>) -> Vec<InformationLeak> {
+    let (card_set, status) = match &action.node {
+        ActionRule::FlipAction { card_set, status } => (card_set, status),
+        ActionRule::Move { move_type } => match move_card_set(move_type) {
+            Some((from, status, _, _)) => (from, status),
+            None => return vec![],
+        },
+        _ => return vec![],
+    };
+
+    if !matches!(status.node, Status::FaceUp) {
+        return vec![];
+    }
+
+    // Private cards on the table belong to somebody else as well
+    let side = Side::of(&card_set.node, table);
+    if side.whose == Whose::Current {
+        return vec![];
+    }
+
+    side.locations
+        .iter()
+        .filter(|location| env.get(*location).is_some_and(|s| s.private))
+        .map(|location| InformationLeak::Revealed {
+            location: location.clone(),
+            span: card_set.span.clone(),
+        })
+        .collect()
+}
+
+fn hidden_condition(expr: &SBoolExpr, env: &Env, table: &HashSet<String>) -> Vec<InformationLeak> {
+    let mut reads = CardReads {
+        table,
+        sides: Vec::new(),
+        skip: 0,
+    };
+    expr.walk(&mut reads);
+
+    let mut locations: Vec<&String> = reads.sides.iter().filter_map(|s| s.hidden(env)).collect();
+    locations.dedup();
+
+    locations
+        .into_iter()
+        .map(|location| InformationLeak::HiddenCondition {
+            location: location.clone(),
+            span: expr.span.clone(),
+        })
+        .collect()
+}
+
+/// Every CardSet and CardPosition a Condition looks into.
+/// The size of a whole Location is public, so `Hand empty` and `size of Hand`
+/// are skipped (skip > 0).
+struct CardReads<'a> {
+    table: &'a HashSet<String>,
+    sides: Vec<Side>,
+    skip: usize,
+}
+
+fn is_size_only<T: Walker>(node: &T) -> bool {
+    let card_set = match node.kind() {
+        Some(NodeKind::AggregateBool(
+            AggregateBool::CardSetEmpty { card_set } | AggregateBool::CardSetNotEmpty { card_set },
+        )) => card_set,
+        Some(NodeKind::Collection(Collection::CardSet { card_set })) => card_set,
+        _ => return false,
+    };
+
+    match &card_set.node {
+        CardSet::Group { group } | CardSet::GroupOwner { group, .. } => {
+            matches!(group.node, Group::Groupable { .. })
+        }
+        CardSet::Memory { .. } => false,
+    }
+}
+
+impl AstPass for CardReads<'_> {
+    fn enter_node<T: Walker>(&mut self, node: &T)
+    where
+        Self: Sized,
+    {
+        if is_size_only(node) {
+            self.skip += 1;
+        }
+        if self.skip > 0 {
+            return;
+        }
+
+        match node.kind() {
+            Some(NodeKind::CardSet(card_set)) => self.sides.push(Side::of(card_set, self.table)),
+            // e.g. `Suite of top(Hand)`
+            Some(NodeKind::CardPosition(card_position)) => {
+                let locations = card_position_locations(card_position, self.table);
+                let whose = match &locations[..] {
+                    [location] if self.table.contains(location) => Whose::Table,
+                    _ => Whose::Current,
+                };
+                self.sides.push(Side {
+                    locations,
+                    whose,
+                    whole: false,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn exit_node<T: Walker>(&mut self, node: &T)
+    where
+        Self: Sized,
+    {
+        if is_size_only(node) {
+            self.skip -= 1;
+        }
+    }
+}
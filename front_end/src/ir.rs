@@ -8,9 +8,11 @@
 ///      sim: /* Your Custom SimStage Logic */
 ///    }
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
 use std::{
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
     fmt::Debug,
 };
 
@@ -23,15 +25,28 @@ use crate::{lower::Lower, spans::*};
 // ===========================================================================
 impl SGame {
     pub fn to_graph(&self) -> Ir<SpannedPayload> {
-        let mut builder: IrBuilder<SpannedPayload> = IrBuilder::default();
-        builder.build_ir(self);
-
-        return builder.fsm;
+        self.to_graph_with_source_map().0
     }
 
     pub fn to_lowered_graph(&self) -> Ir<LoweredPayLoad> {
         Ir::from(self.to_graph())
     }
+
+    /// Same as to_lowered_graph but additionally returns the SourceMap,
+    /// so that errors on a lowered edge can be mapped back to the file.
+    pub fn to_lowered_graph_with_source_map(&self) -> (Ir<LoweredPayLoad>, SourceMap) {
+        let (fsm, source_map) = self.to_graph_with_source_map();
+
+        (Ir::from(fsm), source_map)
+    }
+
+    fn to_graph_with_source_map(&self) -> (Ir<SpannedPayload>, SourceMap) {
+        let mut builder: IrBuilder<SpannedPayload> = IrBuilder::default();
+        builder.build_ir(self);
+        builder.assign_stable_ids();
+
+        (builder.fsm, builder.source_map)
+    }
 }
 
 // ===========================================================================
@@ -39,16 +54,37 @@ impl SGame {
 // ===========================================================================
 pub type Stage = String;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 pub struct StateID(u32);
 
 impl StateID {
     pub fn raw(self) -> u32 {
         self.0
     }
+
+    /// Only for passes that rebuild the IR (see ir_passes).
+    pub(crate) fn from_raw(raw: u32) -> Self {
+        StateID(raw)
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// An Edge is identified by the State it leaves and its position
+/// in the outgoing edges of that State.
+/// Lowering keeps the order of the edges, so an EdgeID of the spanned IR
+/// is also valid for the lowered IR.
+/// StateIDs are stable (see IrBuilder::assign_stable_ids) and the order of the edges
+/// only depends on the FlowComponent that created them, so the EdgeID is stable too.
+#[derive(
+    Copy, Clone, Debug, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+pub struct EdgeID {
+    pub from: StateID,
+    pub index: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(bound = "T: Serialize + DeserializeOwned")] // Tell Serde how to handle the generic
 pub struct Edge<T>
 where
@@ -59,12 +95,12 @@ where
     pub meta: Option<Vec<Meta>>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub enum Meta {
     SimStageEndCondition {
         stage: Stage,
-        end_condition: EndCondition,
-        players: PlayerCollection,
+        end_condition: L::EndCondition,
+        players: L::PlayerCollection,
     }, // Add new Meta-Information here
        // -------------------------------------
 
@@ -74,10 +110,11 @@ pub enum Meta {
 // ===========================================================================
 // Ir-Logic
 // ===========================================================================
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(bound = "T: Serialize + DeserializeOwned")] // Tell Serde how to handle the generic
 pub struct Ir<T: serde::Serialize> {
-    pub states: HashMap<StateID, Vec<Edge<T>>>,
+    // Ordered, so that the output (JSON, DOT, SVG) is deterministic.
+    pub states: BTreeMap<StateID, Vec<Edge<T>>>,
     pub entry: StateID,
     pub goal: StateID,
 }
@@ -85,7 +122,7 @@ pub struct Ir<T: serde::Serialize> {
 impl<T: Serialize + DeserializeOwned> Default for Ir<T> {
     fn default() -> Self {
         Ir {
-            states: HashMap::new(),
+            states: BTreeMap::new(),
             entry: StateID(0),
             goal: StateID(0),
         }
@@ -94,7 +131,14 @@ impl<T: Serialize + DeserializeOwned> Default for Ir<T> {
 
 impl<T: Serialize + DeserializeOwned> Ir<T> {
     /// Both States need to be added before the edge can be added.
-    pub fn add_edge(&mut self, from: StateID, to: StateID, payload: T, meta: Option<Vec<Meta>>) {
+    /// Returns the EdgeID of the new edge.
+    pub fn add_edge(
+        &mut self,
+        from: StateID,
+        to: StateID,
+        payload: T,
+        meta: Option<Vec<Meta>>,
+    ) -> EdgeID {
         let edge = Edge {
             to: to,
             payload: payload,
@@ -106,6 +150,17 @@ impl<T: Serialize + DeserializeOwned> Ir<T> {
             .expect("StateID was not added before!");
 
         vec.push(edge);
+
+        EdgeID {
+            from,
+            index: vec.len() - 1,
+        }
+    }
+
+    pub fn edge(&self, id: EdgeID) -> Option<&Edge<T>> {
+        self.states
+            .get(&id.from)
+            .and_then(|edges| edges.get(id.index))
     }
 
     /// Add state.
@@ -118,6 +173,24 @@ impl<T: Serialize + DeserializeOwned> Ir<T> {
         self.states.remove(&state);
     }
 
+    /// Renames the States (and the targets of the edges).
+    /// States that are not in the map keep their StateID.
+    pub fn rename_states(&mut self, map: &HashMap<StateID, StateID>) {
+        let rename = |state: StateID| map.get(&state).copied().unwrap_or(state);
+
+        self.states = std::mem::take(&mut self.states)
+            .into_iter()
+            .map(|(state, mut edges)| {
+                for edge in edges.iter_mut() {
+                    edge.to = rename(edge.to);
+                }
+                (rename(state), edges)
+            })
+            .collect();
+        self.entry = rename(self.entry);
+        self.goal = rename(self.goal);
+    }
+
     pub fn reachable_from_entry(&self) -> HashSet<StateID>
     where
         StateID: Eq + std::hash::Hash + Copy,
@@ -166,6 +239,87 @@ impl<T: Serialize + DeserializeOwned> Ir<T> {
     }
 }
 
+// ===========================================================================
+// Structural Validation
+// ===========================================================================
+/// Errors in the structure of an IR that was not built by the IrBuilder
+/// (e.g. loaded from a hand-edited or cached file).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum IrStructureError {
+    MissingEntry { entry: StateID },
+    MissingGoal { goal: StateID },
+    DanglingEdge { edge: EdgeID, to: StateID },
+    UnreachableState { state: StateID },
+}
+
+impl std::fmt::Display for IrStructureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IrStructureError::MissingEntry { entry } => {
+                write!(f, "entry state {} does not exist", entry.raw())
+            }
+            IrStructureError::MissingGoal { goal } => {
+                write!(f, "goal state {} does not exist", goal.raw())
+            }
+            IrStructureError::DanglingEdge { edge, to } => write!(
+                f,
+                "edge {} of state {} goes to state {} which does not exist",
+                edge.index,
+                edge.from.raw(),
+                to.raw()
+            ),
+            IrStructureError::UnreachableState { state } => {
+                write!(f, "state {} is not reachable from entry", state.raw())
+            }
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Ir<T> {
+    /// Checks that entry and goal exist, that every edge goes to an existing state
+    /// and that every state is reachable from entry.
+    pub fn structural_errors(&self) -> Vec<IrStructureError> {
+        let mut errs = Vec::new();
+
+        if !self.states.contains_key(&self.entry) {
+            errs.push(IrStructureError::MissingEntry { entry: self.entry });
+        }
+
+        if !self.states.contains_key(&self.goal) {
+            errs.push(IrStructureError::MissingGoal { goal: self.goal });
+        }
+
+        let mut states: Vec<&StateID> = self.states.keys().collect();
+        states.sort();
+
+        for from in states.iter() {
+            for (index, edge) in self.states[from].iter().enumerate() {
+                if !self.states.contains_key(&edge.to) {
+                    errs.push(IrStructureError::DanglingEdge {
+                        edge: EdgeID {
+                            from: **from,
+                            index,
+                        },
+                        to: edge.to,
+                    });
+                }
+            }
+        }
+
+        // Without an entry everything would be unreachable
+        if self.states.contains_key(&self.entry) {
+            let reachable = self.reachable_from_entry();
+            for state in states.iter() {
+                if !reachable.contains(state) {
+                    errs.push(IrStructureError::UnreachableState { state: **state });
+                }
+            }
+        }
+
+        errs
+    }
+}
+
 // ===========================================================================
 // Diagnositics
 // ===========================================================================
@@ -219,6 +373,78 @@ impl Ir<SpannedPayload> {
     }
 }
 
+// ===========================================================================
+// Source-Map
+// ===========================================================================
+/// A single entry of the SourceMap.
+/// The span is the rule/condition/stage the edge was built from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SourceMapEntry {
+    pub edge: EdgeID,
+    pub to: StateID,
+    pub span: OwnedSpan,
+}
+
+/// Maps the edges of the IR back to the spans in the *.cgdsl file.
+/// The lowered IR has no spans anymore, so the backend needs this to report
+/// errors on an edge (e.g. 12 -> 13) at the correct line.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SourceMap {
+    pub edges: Vec<SourceMapEntry>,
+}
+
+impl SourceMap {
+    pub fn insert(&mut self, edge: EdgeID, to: StateID, span: OwnedSpan) {
+        self.edges.push(SourceMapEntry { edge, to, span });
+    }
+
+    pub fn span_of(&self, edge: EdgeID) -> Option<&OwnedSpan> {
+        self.edges
+            .iter()
+            .find(|entry| entry.edge == edge)
+            .map(|entry| &entry.span)
+    }
+
+    /// Same as Ir::rename_states.
+    pub fn rename_states(&mut self, map: &HashMap<StateID, StateID>) {
+        let rename = |state: StateID| map.get(&state).copied().unwrap_or(state);
+
+        for entry in self.edges.iter_mut() {
+            entry.edge.from = rename(entry.edge.from);
+            entry.to = rename(entry.to);
+        }
+    }
+
+    /// Maps the entries to the edges of a transformed IR (see ir_passes).
+    /// Edges that were removed lose their entry.
+    pub fn remap<T: Serialize + DeserializeOwned>(
+        &self,
+        edge_map: &HashMap<EdgeID, EdgeID>,
+        ir: &Ir<T>,
+    ) -> SourceMap {
+        let mut source_map = SourceMap::default();
+        for entry in self.edges.iter() {
+            if let Some(new_id) = edge_map.get(&entry.edge)
+                && let Some(edge) = ir.edge(*new_id)
+            {
+                source_map.insert(*new_id, edge.to, entry.span.clone());
+            }
+        }
+
+        source_map
+    }
+
+    /// All spans of the edges going from one State to another.
+    /// There can be more than one (e.g. Optional with an empty body).
+    pub fn spans_between(&self, from: StateID, to: StateID) -> Vec<&OwnedSpan> {
+        self.edges
+            .iter()
+            .filter(|entry| entry.edge.from == from && entry.to == to)
+            .map(|entry| &entry.span)
+            .collect()
+    }
+}
+
 // ===========================================================================
 // Return-Type
 // ===========================================================================
@@ -243,12 +469,17 @@ pub trait AstContext {
     type EndCondition: Serialize + DeserializeOwned + Debug + Clone;
     type GameRule: Serialize + DeserializeOwned + Debug + Clone;
     type Id: Serialize + DeserializeOwned + Debug + Clone;
+    type Players: Serialize + DeserializeOwned + Debug + Clone;
+    type Betting: Serialize + DeserializeOwned + Debug + Clone;
 }
 
 /// Each Transition/Edge needs to have some guard/payload.
 /// E.g. If we have a condition then the edge's payload is proving the condition.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 #[serde(bound = "Ctx: Serialize + DeserializeOwned")] // Tell Serde how to handle the generic
+#[schemars(bound = "Ctx::Condition: JsonSchema, Ctx::EndCondition: JsonSchema, \
+    Ctx::GameRule: JsonSchema, Ctx::Id: JsonSchema, Ctx::Players: JsonSchema, \
+    Ctx::Betting: JsonSchema")]
 pub enum Payload<Ctx: AstContext> {
     Condition {
         expr: Ctx::Condition,
@@ -264,7 +495,54 @@ pub enum Payload<Ctx: AstContext> {
     EndStage(Ctx::Id),
     Choice,
     Optional,
-    Trigger,
+    /// First-to-claim: the players race for the trigger and the first one claims it.
+    /// The claimant takes the edge with claimed == true (into the body),
+    /// everybody else takes the edge with claimed == false (past the body).
+    /// The lock is released when `stage` starts its next round
+    /// (stage == None: the lock holds for the rest of the game).
+    Trigger {
+        players: Ctx::Players,
+        stage: Option<Ctx::Id>,
+        claimed: bool,
+    },
+    /// A step of a betting round (the round is the same on every step).
+    Bet {
+        round: Ctx::Betting,
+        step: BetStep,
+    },
+}
+
+/// The steps of a BettingRound:
+/// `Settled` leaves the round, `Open` goes to the player whose turn it is,
+/// who does one of `Call`, `Raise` or `Fold`, and `NextPlayer` starts over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum BetStep {
+    /// Everybody that is still in matched the highest stake (or only one player is left)
+    Settled,
+    /// Somebody still has to act
+    Open,
+    /// Match the highest stake (a check if there is nothing to match)
+    Call,
+    /// Match the highest stake and raise it by at least the minimum
+    Raise,
+    /// Give up (out of the current stage)
+    Fold,
+    /// The turn goes to the next player that is still in
+    NextPlayer,
+}
+
+impl std::fmt::Display for BetStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let step = match self {
+            BetStep::Settled => "settled",
+            BetStep::Open => "open",
+            BetStep::Call => "call",
+            BetStep::Raise => "raise",
+            BetStep::Fold => "fold",
+            BetStep::NextPlayer => "next player",
+        };
+        write!(f, "{}", step)
+    }
 }
 
 impl<Ctx: AstContext> Payload<Ctx> {
@@ -294,31 +572,42 @@ impl<Ctx: AstContext> Payload<Ctx> {
             Payload::EndStage(_) => format!("End Counter"),
             Payload::Choice => String::from("Choice"),
             Payload::Optional => String::from("Optional"),
-            Payload::Trigger => String::from("Trigger"),
+            Payload::Trigger { claimed, .. } => {
+                if *claimed {
+                    String::from("Claim Trigger")
+                } else {
+                    String::from("Trigger Claimed by Other")
+                }
+            }
+            Payload::Bet { step, .. } => format!("Bet {:?}", step),
         }
     }
 }
 
 pub type SpannedPayload = Payload<SpannedCtx>;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SpannedCtx;
 impl AstContext for SpannedCtx {
     type Condition = SBoolExpr;
     type EndCondition = SEndCondition;
     type GameRule = SGameRule;
     type Id = SID;
+    type Players = SPlayerCollection;
+    type Betting = SBettingRound;
 }
 
 pub type LoweredPayLoad = Payload<LoweredCtx>;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, JsonSchema)]
 pub struct LoweredCtx;
 impl AstContext for LoweredCtx {
     type Condition = L::BoolExpr;
     type EndCondition = L::EndCondition;
     type GameRule = L::GameRule;
     type Id = String;
+    type Players = L::PlayerCollection;
+    type Betting = L::BettingRound;
 }
 
 // ===========================================================================
@@ -335,6 +624,44 @@ pub enum GameFlowError {
 // ===========================================================================
 // Lower
 // ===========================================================================
+impl Lower<LoweredPayLoad> for SpannedPayload {
+    fn lower(&self) -> LoweredPayLoad {
+        match self {
+            Payload::Condition { expr, negated } => Payload::Condition {
+                expr: expr.lower(),
+                negated: *negated,
+            },
+            Payload::EndCondition {
+                expr,
+                negated,
+                stage,
+            } => Payload::EndCondition {
+                expr: expr.lower(),
+                negated: *negated,
+                stage: stage.lower(),
+            },
+            Payload::Action(a) => Payload::Action(a.lower()),
+            Payload::StageRoundCounter(s) => Payload::StageRoundCounter(s.lower()),
+            Payload::EndStage(s) => Payload::EndStage(s.lower()),
+            Payload::Choice => Payload::Choice,
+            Payload::Optional => Payload::Optional,
+            Payload::Trigger {
+                players,
+                stage,
+                claimed,
+            } => Payload::Trigger {
+                players: players.lower(),
+                stage: stage.as_ref().map(|stage| stage.lower()),
+                claimed: *claimed,
+            },
+            Payload::Bet { round, step } => Payload::Bet {
+                round: round.lower(),
+                step: *step,
+            },
+        }
+    }
+}
+
 impl From<Ir<SpannedPayload>> for Ir<LoweredPayLoad> {
     fn from(value: Ir<SpannedPayload>) -> Self {
         let mut lowered_ir: Ir<LoweredPayLoad> = Ir::default();
@@ -346,32 +673,10 @@ impl From<Ir<SpannedPayload>> for Ir<LoweredPayLoad> {
             .map(|(s, es)| {
                 let mut edges = Vec::new();
                 for e in es.iter() {
-                    let lowered_payload: LoweredPayLoad = match &e.payload {
-                        Payload::Condition { expr, negated } => Payload::Condition {
-                            expr: expr.lower(),
-                            negated: *negated,
-                        },
-                        Payload::EndCondition {
-                            expr,
-                            negated,
-                            stage,
-                        } => Payload::EndCondition {
-                            expr: expr.lower(),
-                            negated: *negated,
-                            stage: stage.lower(),
-                        },
-                        Payload::Action(a) => Payload::Action(a.lower()),
-                        Payload::StageRoundCounter(s) => Payload::StageRoundCounter(s.lower()),
-                        Payload::EndStage(s) => Payload::EndStage(s.lower()),
-                        Payload::Choice => Payload::Choice,
-                        Payload::Optional => Payload::Optional,
-                        Payload::Trigger => Payload::Trigger,
-                    };
-
                     edges.push(Edge {
                         to: e.to,
-                        payload: lowered_payload,
-                        meta: None,
+                        payload: e.payload.lower(),
+                        meta: e.meta.clone(),
                     });
                 }
 
@@ -388,6 +693,11 @@ impl From<Ir<SpannedPayload>> for Ir<LoweredPayLoad> {
 // ===========================================================================
 /// fsm: The current IR being constructed.
 /// stage_exits: Keeping track of stage_exits
+/// stage_players: The enclosing stages and the players that take part in them (for triggers)
+/// source_map: The span of the origin of each edge in fsm
+/// path: The path to the FlowComponent that is currently built
+/// state_paths: The path of every state (used for stable StateIDs)
+/// used_paths: All values of state_paths
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(bound = "T: Serialize + DeserializeOwned")]
 pub struct IrBuilder<T: serde::Serialize> {
@@ -395,7 +705,12 @@ pub struct IrBuilder<T: serde::Serialize> {
     state_counter: u32,
     stage_exits: Vec<u32>,
     stage_to_exit: HashMap<String, u32>,
+    stage_players: Vec<(SID, SPlayerCollection)>,
     pub diagnostics: Vec<GameFlowError>,
+    pub source_map: SourceMap,
+    path: Vec<String>,
+    state_paths: HashMap<u32, String>,
+    used_paths: HashSet<String>,
 }
 
 impl<T: Serialize + DeserializeOwned> Default for IrBuilder<T> {
@@ -405,7 +720,12 @@ impl<T: Serialize + DeserializeOwned> Default for IrBuilder<T> {
             state_counter: 0,
             stage_exits: Vec::new(),
             stage_to_exit: HashMap::new(),
+            stage_players: Vec::new(),
             diagnostics: Vec::new(),
+            source_map: SourceMap::default(),
+            path: Vec::new(),
+            state_paths: HashMap::new(),
+            used_paths: HashSet::new(),
         }
     }
 }
@@ -413,25 +733,155 @@ impl<T: Serialize + DeserializeOwned> Default for IrBuilder<T> {
 impl IrBuilder<SpannedPayload> {
     /// Increments the state_counter.
     /// Adds the the new state (id of state == state_counter) to the FSM.
-    fn new_state(&mut self) -> u32 {
+    /// The role (e.g. "exit", "body") together with the current path identifies the state.
+    fn new_state(&mut self, role: &str) -> u32 {
         self.state_counter += 1;
         self.fsm.add_state(StateID(self.state_counter));
 
+        let mut path = self.path.clone();
+        path.push(role.to_string());
+        let path = path.join("/");
+        // Same path twice (e.g. two stages with the same name)
+        let mut unique = path.clone();
+        let mut n = 1;
+        while self.used_paths.contains(&unique) {
+            unique = format!("{}#{}", path, n);
+            n += 1;
+        }
+        self.used_paths.insert(unique.clone());
+        self.state_paths.insert(self.state_counter, unique);
+
         return self.state_counter;
     }
 
+    /// The state_counter depends on everything that was built before a state.
+    /// So a small change at the start of the game changes every StateID after it.
+    /// This replaces the StateIDs with a hash of the path to the state
+    /// (e.g. "stage:Play/if0/body"), so they only change if the FlowComponent
+    /// or its position among the FlowComponents of the same kind changes (see path_segments).
+    /// Entry (0) and goal (1) are kept.
+    pub fn assign_stable_ids(&mut self) {
+        let mut map = HashMap::new();
+        let mut used: HashSet<u32> = HashSet::from([self.fsm.entry.0, self.fsm.goal.0]);
+
+        let states: Vec<StateID> = self.fsm.states.keys().copied().collect();
+        for state in states {
+            if state == self.fsm.entry || state == self.fsm.goal {
+                continue;
+            }
+            let Some(path) = self.state_paths.get(&state.0) else {
+                continue;
+            };
+
+            let digest = Sha256::digest(path.as_bytes());
+            let mut id = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+            // Collision (very unlikely): take the next free id
+            while id < 2 || used.contains(&id) {
+                id = id.wrapping_add(1);
+            }
+            used.insert(id);
+            map.insert(state, StateID(id));
+        }
+
+        self.fsm.rename_states(&map);
+        self.source_map.rename_states(&map);
+        self.state_paths = std::mem::take(&mut self.state_paths)
+            .into_iter()
+            .map(|(state, path)| {
+                let state = map.get(&StateID(state)).map_or(state, |s| s.0);
+                (state, path)
+            })
+            .collect();
+    }
+
+    /// The path to the FlowComponent that created the state (e.g. "stage:Play/if0/body").
+    /// The entry has no path.
+    pub fn state_path(&self, state: StateID) -> Option<&str> {
+        self.state_paths.get(&state.0).map(|path| path.as_str())
+    }
+
+    /// Name of a FlowComponent in the path.
+    /// Stages are named after the stage, everything else is counted per kind
+    /// (e.g. the second if is "if1"), so adding a rule does not rename an if.
+    /// SetUp-rules are counted separately, so adding one does not rename the other rules.
+    /// The count is positional: inserting a rule in a block renames the states of the
+    /// rules after it in the same block (other blocks and stages are not affected).
+    fn path_segments(flows: &[SFlowComponent]) -> Vec<String> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+
+        flows
+            .iter()
+            .map(|flow| {
+                let kind = match &flow.node {
+                    FlowComponent::SeqStage { stage } => {
+                        return format!("stage:{}", stage.node.stage.node);
+                    }
+                    FlowComponent::SimStage { stage } => {
+                        return format!("stage:{}", stage.node.stage.node);
+                    }
+                    FlowComponent::ChoiceRule { .. } => "choose",
+                    FlowComponent::GameRule { game_rule } => match &game_rule.node {
+                        GameRule::SetUp { .. } => "setup",
+                        _ => "rule",
+                    },
+                    FlowComponent::IfRule { .. } => "if",
+                    FlowComponent::OptionalRule { .. } => "optional",
+                    FlowComponent::TriggerRule { .. } => "trigger",
+                    FlowComponent::BettingRound { .. } => "betting",
+                    FlowComponent::Conditional { .. } => "conditional",
+                };
+                let count = counts.entry(kind).or_insert(0);
+                *count += 1;
+
+                format!("{}{}", kind, *count - 1)
+            })
+            .collect()
+    }
+
+    fn is_setup(flow: &SFlowComponent) -> bool {
+        matches!(
+            &flow.node,
+            FlowComponent::GameRule { game_rule }
+                if matches!(game_rule.node, GameRule::SetUp { .. })
+        )
+    }
+
+    /// The state after the last SetUp-rule is "setup/exit" and not "setup{N-1}/exit",
+    /// so adding a SetUp-rule does not rename the state the game starts from.
+    fn new_setup_exit(&mut self) -> u32 {
+        let segment = self.path.pop();
+        self.path.push(String::from("setup"));
+        let exit = self.new_state("exit");
+        self.path.pop();
+        self.path.extend(segment);
+
+        exit
+    }
+
     /// Decrements the state_counter.
     /// Removes the new state (id of state == state_counter) to the FSM.
     fn remove_state(&mut self, state: u32) -> u32 {
         self.state_counter -= 1;
         self.fsm.remove_state(StateID(state));
+        if let Some(path) = self.state_paths.remove(&state) {
+            self.used_paths.remove(&path);
+        }
 
         return state;
     }
 
     /// Adds edge to the FSM.
-    fn new_edge(&mut self, from: u32, to: u32, payload: SpannedPayload, meta: Option<Vec<Meta>>) {
-        self.fsm.add_edge(StateID(from), StateID(to), payload, meta);
+    /// The span (origin of the edge) is recorded in the source_map.
+    fn new_edge(
+        &mut self,
+        from: u32,
+        to: u32,
+        payload: SpannedPayload,
+        meta: Option<Vec<Meta>>,
+        span: &OwnedSpan,
+    ) {
+        let id = self.fsm.add_edge(StateID(from), StateID(to), payload, meta);
+        self.source_map.insert(id, StateID(to), span.clone());
     }
 
     fn unreachable(&mut self, flows: &[SFlowComponent]) {
@@ -452,7 +902,7 @@ impl IrBuilder<SpannedPayload> {
         self.fsm.add_state(StateID(entry));
 
         // Initialize goal
-        let goal = self.new_state();
+        let goal = self.new_state("goal");
         self.fsm.goal = StateID(goal);
 
         // Build IR
@@ -469,14 +919,26 @@ impl IrBuilder<SpannedPayload> {
     ) -> GameFlowChange {
         let mut next_entry = entry;
         let mut flow_exit;
+        let segments = Self::path_segments(flows);
         for i in 0..flows.len() {
+            self.path.push(segments[i].clone());
             if i == flows.len() - 1 {
                 // Return the last node with its GameFlowChange-type
-                return self.build_flow(&flows[i], next_entry, exit);
+                let change = self.build_flow(&flows[i], next_entry, exit);
+                self.path.pop();
+
+                return change;
             }
 
-            flow_exit = self.new_state();
-            match self.build_flow(&flows[i], next_entry, flow_exit) {
+            flow_exit = if Self::is_setup(&flows[i]) && !Self::is_setup(&flows[i + 1]) {
+                self.new_setup_exit()
+            } else {
+                self.new_state("exit")
+            };
+            let change = self.build_flow(&flows[i], next_entry, flow_exit);
+            self.path.pop();
+
+            match change {
                 GameFlowChange::EndCurrentStage(stage) => {
                     // The flow_exit was not used, so remove it
                     self.remove_state(flow_exit);
@@ -531,10 +993,13 @@ impl IrBuilder<SpannedPayload> {
             }
             FlowComponent::IfRule { if_rule } => self.build_if_rule(&if_rule.node, entry, exit),
             FlowComponent::OptionalRule { optional_rule } => {
-                self.build_optional_rule(&optional_rule.node, entry, exit)
+                self.build_optional_rule(optional_rule, entry, exit)
             }
             FlowComponent::TriggerRule { trigger_rule } => {
-                self.build_trigger_rule(&trigger_rule.node, entry, exit)
+                self.build_trigger_rule(trigger_rule, entry, exit)
+            }
+            FlowComponent::BettingRound { betting_round } => {
+                self.build_betting_round(betting_round, entry, exit)
             }
             FlowComponent::Conditional { conditional } => {
                 self.build_cond_rule(&conditional.node, entry, exit)
@@ -548,12 +1013,14 @@ impl IrBuilder<SpannedPayload> {
         let entry = entry;
         let choice_exit = exit;
 
-        for option in choice_rule.options.iter() {
-            let choice = self.new_state();
+        for (i, option) in choice_rule.options.iter().enumerate() {
+            self.path.push(format!("option{}", i));
+            let choice = self.new_state("entry");
 
-            self.new_edge(entry, choice, Payload::Choice, None);
+            self.new_edge(entry, choice, Payload::Choice, None, &option.span);
 
             self.build_flow(option, choice, choice_exit);
+            self.path.pop();
         }
 
         return choice_exit;
@@ -570,11 +1037,22 @@ impl IrBuilder<SpannedPayload> {
         // Creating a new stage_exit
         self.stage_exits.push(exit);
         self.stage_to_exit.insert(stage_id.node.clone(), exit);
+        // Only the player of the stage can claim a trigger
+        let player = stage.player.clone();
+        self.stage_players.push((
+            stage_id.clone(),
+            SPlayerCollection {
+                span: player.span.clone(),
+                node: PlayerCollection::Literal {
+                    players: vec![player],
+                },
+            },
+        ));
 
         // Check End-Condition Type
         match end_condition.node {
             EndCondition::UntilEnd => {
-                let flows_exit = self.new_state();
+                let flows_exit = self.new_state("flows_exit");
                 // Dont do a split with EndCondition and NotEndCondition
                 match self.build_flows(&stage.flows, entry, flows_exit) {
                     GameFlowChange::None(_) => {
@@ -583,6 +1061,7 @@ impl IrBuilder<SpannedPayload> {
                             entry,
                             Payload::StageRoundCounter(stage_id.clone()),
                             None,
+                            &stage_id.span,
                         );
                     }
                     _ => {}
@@ -590,6 +1069,7 @@ impl IrBuilder<SpannedPayload> {
 
                 // Remove current Stage
                 self.stage_exits.pop();
+                self.stage_players.pop();
 
                 return exit;
             }
@@ -604,9 +1084,10 @@ impl IrBuilder<SpannedPayload> {
                         stage: stage_id.clone(),
                     },
                     None,
+                    &end_condition.span,
                 );
 
-                let else_state = self.new_state();
+                let else_state = self.new_state("else");
 
                 self.new_edge(
                     entry,
@@ -617,9 +1098,10 @@ impl IrBuilder<SpannedPayload> {
                         stage: stage_id.clone(),
                     },
                     None,
+                    &end_condition.span,
                 );
 
-                let flows_exit = self.new_state();
+                let flows_exit = self.new_state("flows_exit");
                 match self.build_flows(&stage.flows, else_state, flows_exit) {
                     GameFlowChange::None(_) => {
                         self.new_edge(
@@ -627,6 +1109,7 @@ impl IrBuilder<SpannedPayload> {
                             entry,
                             Payload::StageRoundCounter(stage_id.clone()),
                             None,
+                            &stage_id.span,
                         );
                     }
                     _ => {}
@@ -634,6 +1117,7 @@ impl IrBuilder<SpannedPayload> {
 
                 // Remove current Stage
                 self.stage_exits.pop();
+                self.stage_players.pop();
 
                 return exit;
             }
@@ -659,11 +1143,13 @@ impl IrBuilder<SpannedPayload> {
         // Creating a new stage_exit
         self.stage_exits.push(exit);
         self.stage_to_exit.insert(stage_id.node.clone(), exit);
+        self.stage_players
+            .push((stage_id.clone(), stage.players.clone()));
 
         // Check End-Condition Type
         match end_condition.node {
             EndCondition::UntilEnd => {
-                let flows_exit = self.new_state();
+                let flows_exit = self.new_state("flows_exit");
                 // Dont do a split with EndCondition and NotEndCondition
                 match self.build_flows(&stage.flows, entry, flows_exit) {
                     GameFlowChange::None(_) => {
@@ -672,6 +1158,7 @@ impl IrBuilder<SpannedPayload> {
                             entry,
                             Payload::StageRoundCounter(stage_id.clone()),
                             None,
+                            &stage_id.span,
                         );
                     }
                     _ => {}
@@ -679,6 +1166,7 @@ impl IrBuilder<SpannedPayload> {
 
                 // Remove current Stage
                 self.stage_exits.pop();
+                self.stage_players.pop();
 
                 return exit;
             }
@@ -693,9 +1181,10 @@ impl IrBuilder<SpannedPayload> {
                         stage: stage_id.clone(),
                     },
                     None,
+                    &end_condition.span,
                 );
 
-                let else_state = self.new_state();
+                let else_state = self.new_state("else");
 
                 self.new_edge(
                     entry,
@@ -706,9 +1195,10 @@ impl IrBuilder<SpannedPayload> {
                         stage: stage_id.clone(),
                     },
                     None,
+                    &end_condition.span,
                 );
 
-                let flows_exit = self.new_state();
+                let flows_exit = self.new_state("flows_exit");
                 match self.build_flows(&stage.flows, else_state, flows_exit) {
                     GameFlowChange::None(_) => {
                         self.new_edge(
@@ -716,6 +1206,7 @@ impl IrBuilder<SpannedPayload> {
                             entry,
                             Payload::StageRoundCounter(stage_id.clone()),
                             None,
+                            &stage_id.span,
                         );
                     }
                     _ => {}
@@ -723,6 +1214,7 @@ impl IrBuilder<SpannedPayload> {
 
                 // Remove current Stage
                 self.stage_exits.pop();
+                self.stage_players.pop();
 
                 return exit;
             }
@@ -750,6 +1242,7 @@ impl IrBuilder<SpannedPayload> {
                                         last_stage_exit,
                                         Payload::Action(rule.clone()),
                                         None,
+                                        &rule.span,
                                     );
 
                                     // Nothing after end stage will be evaluated!
@@ -772,6 +1265,7 @@ impl IrBuilder<SpannedPayload> {
                                         specific_exit,
                                         Payload::Action(rule.clone()),
                                         None,
+                                        &rule.span,
                                     );
 
                                     // Nothing after end stage will be evaluated!
@@ -785,17 +1279,29 @@ impl IrBuilder<SpannedPayload> {
                                     return GameFlowChange::None(exit);
                                 }
                             }
-                            EndType::GameWithWinner { players: _ } => {
+                            EndType::GameWithWinner { .. } | EndType::GameWithTeamWinner { .. } => {
                                 let goal = self.fsm.goal.0;
 
-                                self.new_edge(entry, goal, Payload::Action(rule.clone()), None);
+                                self.new_edge(
+                                    entry,
+                                    goal,
+                                    Payload::Action(rule.clone()),
+                                    None,
+                                    &rule.span,
+                                );
 
                                 // Nothing after end game will be evaluated!
                                 return GameFlowChange::EndGame(goal);
                             }
                             EndType::Turn => {
                                 // Normal action with no GameFlowChange
-                                self.new_edge(entry, exit, Payload::Action(rule.clone()), None);
+                                self.new_edge(
+                                    entry,
+                                    exit,
+                                    Payload::Action(rule.clone()),
+                                    None,
+                                    &rule.span,
+                                );
 
                                 return GameFlowChange::None(exit);
                             }
@@ -803,7 +1309,7 @@ impl IrBuilder<SpannedPayload> {
                     }
                     _ => {
                         // Normal action with no GameFlowChange
-                        self.new_edge(entry, exit, Payload::Action(rule.clone()), None);
+                        self.new_edge(entry, exit, Payload::Action(rule.clone()), None, &rule.span);
 
                         return GameFlowChange::None(exit);
                     }
@@ -811,13 +1317,13 @@ impl IrBuilder<SpannedPayload> {
             }
             GameRule::SetUp { setup: _ } => {
                 // Normal action with no GameFlowChange
-                self.new_edge(entry, exit, Payload::Action(rule.clone()), None);
+                self.new_edge(entry, exit, Payload::Action(rule.clone()), None, &rule.span);
 
                 return GameFlowChange::None(exit);
             }
             GameRule::Scoring { scoring: _ } => {
                 // Normal action with no GameFlowChange
-                self.new_edge(entry, exit, Payload::Action(rule.clone()), None);
+                self.new_edge(entry, exit, Payload::Action(rule.clone()), None, &rule.span);
 
                 return GameFlowChange::None(exit);
             }
@@ -827,7 +1333,7 @@ impl IrBuilder<SpannedPayload> {
     /// GameFlowChanges are handled separately. build_if_rule does not need to worry!
     fn build_if_rule(&mut self, if_rule: &IfRule, entry: u32, exit: u32) -> u32 {
         let condition = if_rule.condition.clone();
-        let if_body = self.new_state();
+        let if_body = self.new_state("body");
 
         self.new_edge(
             entry,
@@ -837,6 +1343,7 @@ impl IrBuilder<SpannedPayload> {
                 negated: false,
             },
             None,
+            &condition.span,
         );
 
         self.new_edge(
@@ -847,6 +1354,7 @@ impl IrBuilder<SpannedPayload> {
                 negated: true,
             },
             None,
+            &condition.span,
         );
 
         self.build_flows(&if_rule.flows, if_body, exit);
@@ -861,7 +1369,12 @@ impl IrBuilder<SpannedPayload> {
         let mut next_entry = entry;
         let mut case_exit = exit;
         for i in 0..cond_rule.cases.len() {
-            case_exit = if i == _len { exit } else { self.new_state() };
+            self.path.push(format!("case{}", i));
+            case_exit = if i == _len {
+                exit
+            } else {
+                self.new_state("exit")
+            };
             match &cond_rule.cases[i].node {
                 Case::NoBool { flows: spanneds } => {
                     self.build_flows(&spanneds, next_entry, exit);
@@ -875,7 +1388,7 @@ impl IrBuilder<SpannedPayload> {
                     bool_expr: spanned,
                     flows: spanneds,
                 } => {
-                    let body = self.new_state();
+                    let body = self.new_state("body");
                     let condition = spanned.clone();
                     self.new_edge(
                         next_entry,
@@ -885,6 +1398,7 @@ impl IrBuilder<SpannedPayload> {
                             negated: false,
                         },
                         None,
+                        &condition.span,
                     );
 
                     self.new_edge(
@@ -895,12 +1409,14 @@ impl IrBuilder<SpannedPayload> {
                             negated: true,
                         },
                         None,
+                        &condition.span,
                     );
 
                     self.build_flows(&spanneds, body, exit);
                 }
             }
 
+            self.path.pop();
             next_entry = case_exit;
         }
 
@@ -908,21 +1424,98 @@ impl IrBuilder<SpannedPayload> {
     }
 
     /// GameFlowChanges are handled separately. build_optional_rule does not need to worry!
-    fn build_optional_rule(&mut self, optional_rule: &OptionalRule, entry: u32, exit: u32) -> u32 {
-        let optional_body = self.new_state();
-        self.new_edge(entry, optional_body, Payload::Optional, None);
-        self.build_flows(&optional_rule.flows, optional_body, exit);
-        self.new_edge(entry, exit, Payload::Optional, None);
+    fn build_optional_rule(&mut self, optional_rule: &SOptionalRule, entry: u32, exit: u32) -> u32 {
+        let optional_body = self.new_state("body");
+        self.new_edge(
+            entry,
+            optional_body,
+            Payload::Optional,
+            None,
+            &optional_rule.span,
+        );
+        self.build_flows(&optional_rule.node.flows, optional_body, exit);
+        self.new_edge(entry, exit, Payload::Optional, None, &optional_rule.span);
 
         return exit;
     }
 
-    /// GameFlowChanges are handled separately. build_optional_rule does not need to worry!
-    fn build_trigger_rule(&mut self, trigger_rule: &TriggerRule, entry: u32, exit: u32) -> u32 {
-        let trigger_body = self.new_state();
-        self.new_edge(entry, trigger_body, Payload::Trigger, None);
-        self.build_flows(&trigger_rule.flows, trigger_body, exit);
+    /// GameFlowChanges are handled separately. build_trigger_rule does not need to worry!
+    /// The players of the innermost stage race for the trigger. Outside of a stage
+    /// every player takes part and the lock is never released.
+    fn build_trigger_rule(&mut self, trigger_rule: &STriggerRule, entry: u32, exit: u32) -> u32 {
+        let (stage, players) = match self.stage_players.last() {
+            Some((stage, players)) => (Some(stage.clone()), players.clone()),
+            None => (
+                None,
+                SPlayerCollection {
+                    span: trigger_rule.span.clone(),
+                    node: PlayerCollection::Aggregate {
+                        aggregate: SAggregatePlayerCollection {
+                            span: trigger_rule.span.clone(),
+                            node: AggregatePlayerCollection::Quantifier {
+                                quantifier: SQuantifier {
+                                    span: trigger_rule.span.clone(),
+                                    node: Quantifier::All,
+                                },
+                            },
+                        },
+                    },
+                },
+            ),
+        };
+
+        let trigger_body = self.new_state("body");
+        self.new_edge(
+            entry,
+            trigger_body,
+            Payload::Trigger {
+                players: players.clone(),
+                stage: stage.clone(),
+                claimed: true,
+            },
+            None,
+            &trigger_rule.span,
+        );
+        self.build_flows(&trigger_rule.node.flows, trigger_body, exit);
+        self.new_edge(
+            entry,
+            exit,
+            Payload::Trigger {
+                players,
+                stage,
+                claimed: false,
+            },
+            None,
+            &trigger_rule.span,
+        );
 
         return exit;
     }
+
+    /// The round is a loop: entry -> turn -> acted -> entry until it is settled.
+    fn build_betting_round(&mut self, betting_round: &SBettingRound, entry: u32, exit: u32) -> u32 {
+        let turn = self.new_state("turn");
+        let acted = self.new_state("acted");
+
+        let mut bet = |from, to, step| {
+            self.new_edge(
+                from,
+                to,
+                Payload::Bet {
+                    round: betting_round.clone(),
+                    step,
+                },
+                None,
+                &betting_round.span,
+            );
+        };
+        bet(entry, exit, BetStep::Settled);
+        bet(entry, turn, BetStep::Open);
+        bet(turn, acted, BetStep::Call);
+        bet(turn, acted, BetStep::Raise);
+        bet(turn, acted, BetStep::Fold);
+        bet(acted, entry, BetStep::NextPlayer);
+
+        exit
+    }
 }
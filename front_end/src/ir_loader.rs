@@ -0,0 +1,149 @@
+///    Loading the lowered IR (in its envelope) from JSON, RON or bincode.
+///
+///    An IR that was not built by the IrBuilder (hand-edited or cached files)
+///    can not be trusted. So every loaded IR is checked:
+///    - format_version has to match IR_FORMAT_VERSION (it is read first, so an
+///      older format is reported as such and not as a parse error)
+///    - structural validation (see Ir::structural_errors)
+
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::ir::IrStructureError;
+use crate::schema::{IR_FORMAT_VERSION, IrEnvelope};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrFormat {
+    Json,
+    Ron,
+    Bincode,
+}
+
+impl IrFormat {
+    /// Guess the format from the file extension (*.json, *.ron, *.bin, *.bincode).
+    pub fn from_path(path: &Path) -> Option<IrFormat> {
+        match path.extension()?.to_str()? {
+            "json" => Some(IrFormat::Json),
+            "ron" => Some(IrFormat::Ron),
+            "bin" | "bincode" => Some(IrFormat::Bincode),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum IrLoadError {
+    Io(std::io::Error),
+    UnknownFormat,
+    Json(serde_json::Error),
+    Ron(ron::error::SpannedError),
+    Bincode(bincode::Error),
+    FormatVersion { found: u32, expected: u32 },
+    Structure(Vec<IrStructureError>),
+}
+
+impl fmt::Display for IrLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IrLoadError::Io(e) => write!(f, "could not read IR: {}", e),
+            IrLoadError::UnknownFormat => {
+                write!(
+                    f,
+                    "unknown IR format (expected .json, .ron, .bin or .bincode)"
+                )
+            }
+            IrLoadError::Json(e) => write!(f, "invalid JSON: {}", e),
+            IrLoadError::Ron(e) => write!(f, "invalid RON: {}", e),
+            IrLoadError::Bincode(e) => write!(f, "invalid bincode: {}", e),
+            IrLoadError::FormatVersion { found, expected } => write!(
+                f,
+                "IR has format_version {} but {} is expected",
+                found, expected
+            ),
+            IrLoadError::Structure(errs) => {
+                write!(f, "invalid IR:")?;
+                for e in errs.iter() {
+                    write!(f, "\n  - {}", e)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for IrLoadError {}
+
+/// Loads and validates an IR file. The format is taken from the file extension.
+pub fn load_ir(path: &Path) -> Result<IrEnvelope, IrLoadError> {
+    let format = IrFormat::from_path(path).ok_or(IrLoadError::UnknownFormat)?;
+    let bytes = fs::read(path).map_err(IrLoadError::Io)?;
+
+    load_ir_from_bytes(&bytes, format)
+}
+
+/// Only the format_version of an IrEnvelope (every other field is ignored).
+#[derive(Deserialize)]
+#[serde(rename = "IrEnvelope")]
+struct VersionProbe {
+    format_version: u32,
+}
+
+/// Reads the format_version without the rest of the envelope, whose layout
+/// depends on the version.
+fn format_version(bytes: &[u8], format: IrFormat) -> Result<u32, IrLoadError> {
+    let version = match format {
+        IrFormat::Json => {
+            let probe: VersionProbe = serde_json::from_slice(bytes).map_err(IrLoadError::Json)?;
+            probe.format_version
+        }
+        IrFormat::Ron => {
+            let probe: VersionProbe = ron::de::from_bytes(bytes).map_err(IrLoadError::Ron)?;
+            probe.format_version
+        }
+        // format_version is the first field
+        IrFormat::Bincode => bincode::deserialize(bytes).map_err(IrLoadError::Bincode)?,
+    };
+
+    Ok(version)
+}
+
+/// Loads and validates an IR.
+pub fn load_ir_from_bytes(bytes: &[u8], format: IrFormat) -> Result<IrEnvelope, IrLoadError> {
+    let found = format_version(bytes, format)?;
+    if found != IR_FORMAT_VERSION {
+        return Err(IrLoadError::FormatVersion {
+            found,
+            expected: IR_FORMAT_VERSION,
+        });
+    }
+
+    let envelope: IrEnvelope = match format {
+        IrFormat::Json => serde_json::from_slice(bytes).map_err(IrLoadError::Json)?,
+        IrFormat::Ron => ron::de::from_bytes(bytes).map_err(IrLoadError::Ron)?,
+        IrFormat::Bincode => bincode::deserialize(bytes).map_err(IrLoadError::Bincode)?,
+    };
+
+    validate_envelope(&envelope)?;
+
+    Ok(envelope)
+}
+
+pub fn validate_envelope(envelope: &IrEnvelope) -> Result<(), IrLoadError> {
+    if envelope.format_version != IR_FORMAT_VERSION {
+        return Err(IrLoadError::FormatVersion {
+            found: envelope.format_version,
+            expected: IR_FORMAT_VERSION,
+        });
+    }
+
+    let errs = envelope.ir.structural_errors();
+    if !errs.is_empty() {
+        return Err(IrLoadError::Structure(errs));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,388 @@
+///    Optional optimization passes over the IR.
+///
+///    The IrBuilder creates a lot of States that do not matter for the game
+///    (e.g. a `choose` with only one option or two identical branches).
+///    The passes make the IR smaller without changing its behaviour:
+///    - EliminateEpsilon: a Choice/Optional edge that is the only edge of its State
+///      does not give anybody a choice, so both States are collapsed.
+///      Identical parallel Choice/Optional edges are collapsed first.
+///      Triggers are kept, because they tell the backend who claims the rule.
+///    - MergeEquivalent: bisimulation minimization (partition refinement).
+///      Two States are merged if they are both (not) the goal and their edges
+///      (payload, meta and the class of the target) are the same and in the same order.
+///    - Renumber: dense StateIDs in BFS-order (entry = 0, goal = 1).
+///
+///    Every pass keeps track of the EdgeIDs, so the SourceMap can be remapped
+///    (see SourceMap::remap).
+
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::ir::{AstContext, Edge, EdgeID, Ir, Payload, StateID};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IrPass {
+    EliminateEpsilon,
+    MergeEquivalent,
+    Renumber,
+}
+
+impl IrPass {
+    /// Merging can produce new epsilon-edges, so EliminateEpsilon runs twice.
+    pub fn default_pipeline() -> Vec<IrPass> {
+        vec![
+            IrPass::EliminateEpsilon,
+            IrPass::MergeEquivalent,
+            IrPass::EliminateEpsilon,
+            IrPass::Renumber,
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrSize {
+    pub states: usize,
+    pub edges: usize,
+}
+
+impl IrSize {
+    pub fn of<T: Serialize + DeserializeOwned>(ir: &Ir<T>) -> Self {
+        IrSize {
+            states: ir.states.len(),
+            edges: ir.states.values().map(|edges| edges.len()).sum(),
+        }
+    }
+}
+
+impl fmt::Display for IrSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} states, {} edges", self.states, self.edges)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    pub pass: IrPass,
+    pub before: IrSize,
+    pub after: IrSize,
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizationReport {
+    pub passes: Vec<PassReport>,
+    /// EdgeID in the original IR -> EdgeID in the optimized IR.
+    /// Removed edges are missing.
+    pub edge_map: HashMap<EdgeID, EdgeID>,
+}
+
+impl OptimizationReport {
+    pub fn before(&self) -> Option<IrSize> {
+        self.passes.first().map(|p| p.before)
+    }
+
+    pub fn after(&self) -> Option<IrSize> {
+        self.passes.last().map(|p| p.after)
+    }
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, report) in self.passes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:?}: {} -> {}",
+                report.pass, report.before, report.after
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs the passes in the given order.
+pub fn optimize<Ctx>(ir: &mut Ir<Payload<Ctx>>, passes: &[IrPass]) -> OptimizationReport
+where
+    Ctx: AstContext,
+    Payload<Ctx>: Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    let mut edge_map: HashMap<EdgeID, EdgeID> = all_edge_ids(ir).map(|id| (id, id)).collect();
+    let mut reports = Vec::new();
+
+    for pass in passes.iter() {
+        let before = IrSize::of(ir);
+        let pass_map = match pass {
+            IrPass::EliminateEpsilon => eliminate_epsilon(ir),
+            IrPass::MergeEquivalent => merge_equivalent(ir),
+            IrPass::Renumber => renumber(ir),
+        };
+
+        // Compose: original -> current -> new
+        edge_map = edge_map
+            .into_iter()
+            .filter_map(|(original, current)| pass_map.get(&current).map(|new| (original, *new)))
+            .collect();
+
+        reports.push(PassReport {
+            pass: *pass,
+            before,
+            after: IrSize::of(ir),
+        });
+    }
+
+    OptimizationReport {
+        passes: reports,
+        edge_map,
+    }
+}
+
+fn all_edge_ids<T: Serialize + DeserializeOwned>(ir: &Ir<T>) -> impl Iterator<Item = EdgeID> + '_ {
+    ir.states
+        .iter()
+        .flat_map(|(from, edges)| (0..edges.len()).map(|index| EdgeID { from: *from, index }))
+}
+
+fn sorted_states<T: Serialize + DeserializeOwned>(ir: &Ir<T>) -> Vec<StateID> {
+    ir.states.keys().copied().collect()
+}
+
+fn is_epsilon<Ctx: AstContext>(payload: &Payload<Ctx>) -> bool {
+    matches!(payload, Payload::Choice | Payload::Optional)
+}
+
+// ===========================================================================
+// EliminateEpsilon
+// ===========================================================================
+fn eliminate_epsilon<Ctx>(ir: &mut Ir<Payload<Ctx>>) -> HashMap<EdgeID, EdgeID>
+where
+    Ctx: AstContext,
+    Payload<Ctx>: Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    let mut edge_map: HashMap<EdgeID, EdgeID> = all_edge_ids(ir).map(|id| (id, id)).collect();
+
+    // Identical parallel Choice/Optional edges (e.g. after merging two branches)
+    for state in sorted_states(ir) {
+        let edges = ir.states.remove(&state).unwrap_or_default();
+        let mut kept: Vec<Edge<Payload<Ctx>>> = Vec::new();
+        let mut index_map = Vec::new();
+
+        for edge in edges.into_iter() {
+            let duplicate = kept.iter().position(|k| {
+                is_epsilon(&edge.payload)
+                    && k.to == edge.to
+                    && k.payload == edge.payload
+                    && k.meta == edge.meta
+            });
+            match duplicate {
+                Some(index) => index_map.push(index),
+                None => {
+                    index_map.push(kept.len());
+                    kept.push(edge);
+                }
+            }
+        }
+
+        ir.states.insert(state, kept);
+        for (old, new) in index_map.into_iter().enumerate() {
+            edge_map.insert(
+                EdgeID {
+                    from: state,
+                    index: old,
+                },
+                EdgeID {
+                    from: state,
+                    index: new,
+                },
+            );
+        }
+    }
+
+    // Collapse a State into the target of its only (epsilon-)edge
+    loop {
+        let candidate =
+            sorted_states(ir)
+                .into_iter()
+                .find_map(|state| match ir.states[&state].as_slice() {
+                    [edge] if is_epsilon(&edge.payload) && edge.to != state => {
+                        Some((state, edge.to))
+                    }
+                    _ => None,
+                });
+        let Some((state, target)) = candidate else {
+            break;
+        };
+
+        ir.states.remove(&state);
+        for edges in ir.states.values_mut() {
+            for edge in edges.iter_mut().filter(|e| e.to == state) {
+                edge.to = target;
+            }
+        }
+        if ir.entry == state {
+            ir.entry = target;
+        }
+
+        edge_map.retain(|_, current| current.from != state);
+    }
+
+    edge_map
+}
+
+// ===========================================================================
+// MergeEquivalent
+// ===========================================================================
+fn merge_equivalent<Ctx>(ir: &mut Ir<Payload<Ctx>>) -> HashMap<EdgeID, EdgeID>
+where
+    Ctx: AstContext,
+    Payload<Ctx>: Clone + PartialEq + Serialize + DeserializeOwned,
+{
+    let states = sorted_states(ir);
+    let mut class: HashMap<StateID, usize> = states
+        .iter()
+        .map(|s| (*s, if *s == ir.goal { 0 } else { 1 }))
+        .collect();
+    let mut class_count = class.values().collect::<HashSet<_>>().len();
+
+    // Partition refinement until the number of classes does not change anymore
+    loop {
+        let mut representatives: Vec<StateID> = Vec::new();
+        let mut new_class: HashMap<StateID, usize> = HashMap::new();
+
+        for state in states.iter() {
+            let found = representatives
+                .iter()
+                .position(|rep| equivalent(ir, &class, *rep, *state));
+            let id = match found {
+                Some(id) => id,
+                None => {
+                    representatives.push(*state);
+                    representatives.len() - 1
+                }
+            };
+            new_class.insert(*state, id);
+        }
+
+        let done = representatives.len() == class_count;
+        class = new_class;
+        class_count = representatives.len();
+        if done {
+            break;
+        }
+    }
+
+    // Smallest StateID of every class represents it
+    let mut representative: HashMap<usize, StateID> = HashMap::new();
+    for state in states.iter() {
+        representative.entry(class[state]).or_insert(*state);
+    }
+    let rep_of = |state: &StateID| representative[&class[state]];
+
+    let mut edge_map = HashMap::new();
+    let mut merged: BTreeMap<StateID, Vec<Edge<Payload<Ctx>>>> = BTreeMap::new();
+    for state in states.iter() {
+        let rep = rep_of(state);
+        let edges = &ir.states[state];
+        for index in 0..edges.len() {
+            edge_map.insert(
+                EdgeID {
+                    from: *state,
+                    index,
+                },
+                EdgeID { from: rep, index },
+            );
+        }
+        if rep == *state {
+            let edges = edges
+                .iter()
+                .map(|edge| Edge {
+                    to: rep_of(&edge.to),
+                    payload: edge.payload.clone(),
+                    meta: edge.meta.clone(),
+                })
+                .collect();
+            merged.insert(rep, edges);
+        }
+    }
+
+    ir.entry = rep_of(&ir.entry);
+    ir.goal = rep_of(&ir.goal);
+    ir.states = merged;
+
+    edge_map
+}
+
+fn equivalent<Ctx>(
+    ir: &Ir<Payload<Ctx>>,
+    class: &HashMap<StateID, usize>,
+    a: StateID,
+    b: StateID,
+) -> bool
+where
+    Ctx: AstContext,
+    Payload<Ctx>: PartialEq + Serialize + DeserializeOwned,
+{
+    if class[&a] != class[&b] {
+        return false;
+    }
+
+    let (edges_a, edges_b) = (&ir.states[&a], &ir.states[&b]);
+
+    edges_a.len() == edges_b.len()
+        && edges_a.iter().zip(edges_b.iter()).all(|(x, y)| {
+            x.payload == y.payload && x.meta == y.meta && class.get(&x.to) == class.get(&y.to)
+        })
+}
+
+// ===========================================================================
+// Renumber
+// ===========================================================================
+fn renumber<T: Serialize + DeserializeOwned>(ir: &mut Ir<T>) -> HashMap<EdgeID, EdgeID> {
+    let mut new_id: HashMap<StateID, StateID> = HashMap::new();
+    let assign = |state: StateID, new_id: &mut HashMap<StateID, StateID>| {
+        if ir.states.contains_key(&state) && !new_id.contains_key(&state) {
+            new_id.insert(state, StateID::from_raw(new_id.len() as u32));
+            true
+        } else {
+            false
+        }
+    };
+
+    assign(ir.entry, &mut new_id);
+    assign(ir.goal, &mut new_id);
+
+    // BFS in the order of the edges
+    let mut queue = VecDeque::from([ir.entry]);
+    while let Some(state) = queue.pop_front() {
+        for edge in ir.states.get(&state).into_iter().flatten() {
+            if assign(edge.to, &mut new_id) {
+                queue.push_back(edge.to);
+            }
+        }
+    }
+    // Unreachable states at the end (they are reported by the validation)
+    for state in sorted_states(ir) {
+        assign(state, &mut new_id);
+    }
+
+    let edge_map = all_edge_ids(ir)
+        .map(|id| {
+            let from = new_id[&id.from];
+            (
+                id,
+                EdgeID {
+                    from,
+                    index: id.index,
+                },
+            )
+        })
+        .collect();
+    ir.rename_states(&new_id);
+
+    edge_map
+}
@@ -11,11 +11,20 @@ pub mod fmt_ast;
 pub mod lower;
 pub mod parser;
 include!("ast.rs");
+pub mod card_count;
+pub mod diff;
+pub mod filter_eval;
 pub mod fsm_to_dot;
+pub mod information_flow;
 pub mod ir;
+pub mod ir_loader;
+pub mod ir_passes;
+pub mod schema;
 pub mod semantic;
 pub mod spans;
 pub mod symbols;
+pub mod tables;
+pub mod termination;
 pub mod validation;
 pub mod walker;
 
@@ -1,21 +1,41 @@
+use front_end::ir_passes::{IrPass, optimize};
 use front_end::parser::{CGDSLParser, Rule};
+use front_end::schema::{IrEnvelope, ir_schema_json};
 use pest_consume::*;
 use std::env;
 use std::fs;
 use std::io::{self, Read};
+use std::path::Path;
 
 fn main() {
     let args: Vec<_> = env::args().collect();
 
-    let input: String = if args.len() > 1 {
+    // --optimize can be given anywhere, the other arguments are positional
+    let optimized = args.iter().any(|a| a == "--optimize");
+    let args: Vec<_> = args.into_iter().filter(|a| a != "--optimize").collect();
+
+    // Print the JSON Schema of the output and exit
+    if args.len() > 1 && args[1] == "--schema" {
+        print!("{}", ir_schema_json());
+        return;
+    }
+
+    let (input, name): (String, String) = if args.len() > 1 {
         let input_file = &args[1];
-        fs::read_to_string(input_file).expect("Failed to read file")
+        let name = Path::new(input_file)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        (
+            fs::read_to_string(input_file).expect("Failed to read file"),
+            name,
+        )
     } else {
         let mut stdin = String::new();
         io::stdin()
             .read_to_string(&mut stdin)
             .expect("Failed to read stdin");
-        stdin
+        (stdin, String::from("stdin"))
     };
 
     let game = CGDSLParser::parse(Rule::file, &input)
@@ -24,8 +44,14 @@ fn main() {
         .expect("Expected single");
     let game = CGDSLParser::file(game).expect("Map failed");
 
-    let graph = game.to_lowered_graph();
-    let json = serde_json::to_string_pretty(&graph).expect("Serialization failed");
+    let (mut graph, mut source_map) = game.to_lowered_graph_with_source_map();
+    if optimized {
+        let report = optimize(&mut graph, &IrPass::default_pipeline());
+        source_map = source_map.remap(&report.edge_map, &graph);
+        eprintln!("{}", report);
+    }
+    let envelope = IrEnvelope::new(&name, &game, graph);
+    let json = serde_json::to_string_pretty(&envelope).expect("Serialization failed");
 
     if args.len() > 2 {
         let output_file = &args[2];
@@ -33,4 +59,11 @@ fn main() {
     } else {
         println!("{}", json);
     }
+
+    // Optional: Source-Map (edge -> span in the input file)
+    if args.len() > 3 {
+        let source_map_file = &args[3];
+        let source_map = serde_json::to_string_pretty(&source_map).expect("Serialization failed");
+        fs::write(source_map_file, &source_map).expect("Failed to write");
+    }
 }
@@ -66,6 +66,7 @@ impl CGDSLParser {
                 [choice_rule(t)] => FlowComponent::ChoiceRule { choice_rule: t },
                 [optional_rule(l)] => FlowComponent::OptionalRule { optional_rule: l },
                 [trigger_rule(l)] => FlowComponent::TriggerRule { trigger_rule: l },
+                [betting_round(b)] => FlowComponent::BettingRound { betting_round: b },
                 [game_rule(k)] => FlowComponent::GameRule { game_rule: k },
                 [cond_rule(k)] => FlowComponent::Conditional { conditional: k },
         );
@@ -184,6 +185,34 @@ impl CGDSLParser {
         Ok(STriggerRule { node: node, span })
     }
 
+    pub(crate) fn kw_betting(input: Node) -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn kw_minimum(input: Node) -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn stake(input: Node) -> Result<SStake> {
+        let span = OwnedSpan::from(input.as_span());
+        let node = match_nodes!(input.into_children();
+            [kw_with(_), token(t), kw_from(_), location(from), kw_to(_), location(to)] => Stake::Tokens { token: t, from, to },
+            [kw_on(_), memory(m)] => Stake::Memory { memory: m },
+        );
+
+        Ok(SStake { node, span })
+    }
+
+    pub(crate) fn betting_round(input: Node) -> Result<SBettingRound> {
+        let span = OwnedSpan::from(input.as_span());
+        let node = match_nodes!(input.into_children();
+            [kw_betting(_), kw_for(_), player_collection(p), stake(s)] => BettingRound::Bet { players: p, stake: s },
+            [kw_betting(_), kw_for(_), player_collection(p), stake(s), kw_minimum(_), int_expr(i)] => BettingRound::BetMinimum { players: p, stake: s, minimum: Box::new(i) },
+        );
+
+        Ok(SBettingRound { node, span })
+    }
+
     pub(crate) fn optional_rule(input: Node) -> Result<SOptionalRule> {
         let span = OwnedSpan::from(input.as_span());
         let node = match_nodes!(input.into_children();
@@ -434,6 +463,22 @@ impl CGDSLParser {
         ))
     }
 
+    pub(crate) fn kw_follow(input: Node) -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn kw_trump(input: Node) -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn trick_winner(input: Node) -> Result<SCardPosition> {
+        let span = OwnedSpan::from(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [kw_winner(_), kw_of(_), card_set(s), kw_using(_), precedence(p), kw_follow(_), key(k), kw_is(_), string_expr(l)] => saggregate_card_position(AggregateCardPosition::TrickWinner { card_set: Box::new(s), precedence: p, key: k, lead: Box::new(l) }, span),
+            [kw_winner(_), kw_of(_), card_set(s), kw_using(_), precedence(p), kw_trump(_), string_expr(t), kw_follow(_), key(k), kw_is(_), string_expr(l)] => saggregate_card_position(AggregateCardPosition::TrickWinnerTrump { card_set: Box::new(s), precedence: p, trump: Box::new(t), key: k, lead: Box::new(l) }, span),
+        ))
+    }
+
     pub(crate) fn location_int_expr(input: Node) -> Result<SCardPosition> {
         let span = OwnedSpan::from(input.as_span());
         Ok(match_nodes!(input.into_children();
@@ -447,6 +492,7 @@ impl CGDSLParser {
             [location_bottom(n)] => n,
             [location_int_expr(n)] => n,
             [extrema_of_card_set(n)] => n,
+            [trick_winner(n)] => n,
         ))
     }
 
@@ -607,6 +653,17 @@ impl CGDSLParser {
         ))
     }
 
+    pub(crate) fn kw_tokens(input: Node) -> Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn token_count(input: Node) -> Result<SIntExpr> {
+        let span = OwnedSpan::from(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [kw_tokens(_), token(t), kw_in(_), token_loc(l)] => saggregate_int(AggregateInt::TokenCount { token: t, token_loc: Box::new(l) }, span),
+        ))
+    }
+
     pub(crate) fn extrema_of_int_collection(input: Node) -> Result<SIntExpr> {
         let span = OwnedSpan::from(input.as_span());
         Ok(match_nodes!(input.into_children();
@@ -657,6 +714,7 @@ impl CGDSLParser {
             [sum_of_int_collection(n)] => n,
             [int_extrema_of_card_set(n)] => n,
             [extrema_of_int_collection(n)] => n,
+            [token_count(n)] => n,
             [runtime_int(n)] => n,
             [int(n)] => n,
             [int_memory(m)] => SIntExpr { node: IntExpr::Memory { memory: m }, span },
@@ -887,6 +945,14 @@ impl CGDSLParser {
         ))
     }
 
+    pub(crate) fn teams(input: Node) -> Result<STeams> {
+        let span = OwnedSpan::from(input.as_span());
+        Ok(match_nodes!(input.into_children();
+            [team_expr(n)] => STeams { node: Teams::Team { team: n }, span },
+            [team_collection(n)] => STeams { node: Teams::TeamCollection { team_collection: n }, span },
+        ))
+    }
+
     pub(crate) fn kw_all(input: Node) -> Result<()> {
         Ok(())
     }
@@ -1025,7 +1091,7 @@ impl CGDSLParser {
     pub(crate) fn key_distinct(input: Node) -> Result<SFilterExpr> {
         let span = OwnedSpan::from(input.as_span());
         Ok(match_nodes!(input.children();
-            [kw_distinct(_), key(key)] => saggregate_filter(AggregateFilter::Same { key: key }, span),
+            [kw_distinct(_), key(key)] => saggregate_filter(AggregateFilter::Distinct { key: key }, span),
         ))
     }
 
@@ -1213,6 +1279,13 @@ impl CGDSLParser {
         ))
     }
 
+    pub(crate) fn key_follow(input: Node) -> Result<SFilterExpr> {
+        let span = OwnedSpan::from(input.as_span());
+        Ok(match_nodes!(input.children();
+            [kw_follow(_), key(key), kw_is(_), string_expr(s)] => saggregate_filter(AggregateFilter::FollowKey { key, string: Box::new(s) }, span),
+        ))
+    }
+
     pub(crate) fn filter_combo(input: Node) -> Result<SFilterExpr> {
         let span = OwnedSpan::from(input.as_span());
         Ok(match_nodes!(input.children();
@@ -1249,6 +1322,7 @@ impl CGDSLParser {
             [key_lower(l)] => l,
             [size_int(si)] => si,
             [key_string(st)] => st,
+            [key_follow(fo)] => fo,
             [filter_combo(fc)] => fc,
             [filter_bin_op(fb)] => fb,
         ))
@@ -1884,6 +1958,7 @@ impl CGDSLParser {
             [kw_stage(_)] => EndType::CurrentStage,
             [stage(s)] => EndType::Stage { stage: s },
             [kw_game(_), kw_with(_), kw_winner(_), players(p)] => EndType::GameWithWinner { players: p },
+            [kw_game(_), kw_with(_), kw_winner(_), teams(t)] => EndType::GameWithTeamWinner { teams: t },
 
         );
 
@@ -2055,6 +2130,8 @@ impl CGDSLParser {
         let node = match_nodes!(input.into_children();
             [kw_score(_), int_expr(n), kw_to(_), memory(memory), kw_of(_), players(o)] => ScoreRule::ScoreMemory { int: n, memory: memory, players: o },
             [kw_score(_), int_expr(n), kw_to(_), players(o)] => ScoreRule::Score{int: n, players: o},
+            [kw_score(_), int_expr(n), kw_to(_), memory(memory), kw_of(_), teams(t)] => ScoreRule::ScoreMemoryTeams { int: n, memory, teams: t },
+            [kw_score(_), int_expr(n), kw_to(_), teams(t)] => ScoreRule::ScoreTeams { int: n, teams: t },
         );
 
         Ok(SScoreRule { node: node, span })
@@ -2069,6 +2146,8 @@ impl CGDSLParser {
         let node = match_nodes!(input.into_children();
             [kw_winner(_), kw_is(_), extrema(e), winner_type(w)] => WinnerRule::WinnerWith { extrema: e, winner_type: w },
             [kw_winner(_), kw_is(_), players(p)] => WinnerRule::Winner { players: p },
+            [kw_winner(_), kw_is(_), teams(t)] => WinnerRule::WinnerTeams { teams: t },
+            [kw_winner(_), kw_is(_), extrema(e), kw_team(_), winner_type(w)] => WinnerRule::WinnerTeamWith { extrema: e, winner_type: w },
         );
 
         Ok(SWinnerRule { node: node, span })
@@ -0,0 +1,77 @@
+///    The lowered IR is the interface to the backend.
+///    The shape of its JSON depends on the AST and the serde defaults,
+///    so it is wrapped in a versioned envelope and described by a JSON Schema.
+///
+///    If the schema changes (e.g. a new AST-variant), bump IR_FORMAT_VERSION
+///    and commit the new schema (test_ir_schema_compatibility tells you how).
+
+
+use schemars::{JsonSchema, Schema, schema_for};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::ast::ast_spanned::SGame;
+use crate::ir::{Ir, LoweredPayLoad};
+use crate::lower::Lower;
+
+/// Version of the JSON format of the lowered IR.
+pub const IR_FORMAT_VERSION: u32 = 6;
+
+/// Directory (relative to the front_end crate) with one schema per format_version.
+pub const SCHEMA_DIR: &str = "schema";
+
+/// The JSON that is given to the backend.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct IrEnvelope {
+    /// Version of this format. Changes whenever the schema changes.
+    pub format_version: u32,
+    /// Name of the game (e.g. the file name without extension)
+    pub game: String,
+    /// SHA-256 of the formatted game (ignores whitespace and comments).
+    pub source_hash: String,
+    /// The lowered IR
+    pub ir: Ir<LoweredPayLoad>,
+}
+
+impl IrEnvelope {
+    pub fn from_game(name: &str, game: &SGame) -> Self {
+        Self::new(name, game, game.to_lowered_graph())
+    }
+
+    /// Wraps an already lowered IR of the game.
+    pub fn new(name: &str, game: &SGame, ir: Ir<LoweredPayLoad>) -> Self {
+        IrEnvelope {
+            format_version: IR_FORMAT_VERSION,
+            game: name.to_string(),
+            source_hash: source_hash(game),
+            ir,
+        }
+    }
+}
+
+/// The hash is taken over the formatted (lowered) game and not over the text.
+/// This way the CLI and the LSP agree on the hash and reformatting
+/// a file does not change it.
+pub fn source_hash(game: &SGame) -> String {
+    let formatted = format!("{}", game.lower());
+    let digest = Sha256::digest(formatted.as_bytes());
+
+    digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn ir_schema() -> Schema {
+    schema_for!(IrEnvelope)
+}
+
+/// Pretty printed schema (the way it is stored in SCHEMA_DIR).
+pub fn ir_schema_json() -> String {
+    let mut json = serde_json::to_string_pretty(&ir_schema()).expect("Serialization failed");
+    json.push('\n');
+
+    json
+}
+
+/// File name of the schema for a format_version.
+pub fn schema_file_name(version: u32) -> String {
+    format!("ir_v{}.json", version)
+}
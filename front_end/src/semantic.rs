@@ -11,11 +11,12 @@ use std::{collections::HashMap, fmt};
 
 use crate::{
     ast::ast_spanned::{NodeKind, *},
-    spans::OwnedSpan,
+    spans::{OwnedSpan, SID},
     symbols::Var,
     walker::AstPass,
 };
 
+#[derive(Debug)]
 pub enum SemanticError {
     KeyNotFoundForType { ty: String, key: Var },
     NoCorrToType { ty: Var, key: Var },
@@ -197,6 +198,44 @@ impl SemanticVisitor {
 
         return errs;
     }
+
+    /// A StringExpr that is compared with the Value of a Key:
+    /// a literal has to be a Value of the Key, `Key of ...` has to be the same Key.
+    fn use_string_as_value_of(&mut self, key: &SID, string: &SStringExpr) {
+        match &string.node {
+            StringExpr::Query { query: q } => {
+                if let QueryString::KeyOf {
+                    key: k,
+                    card_position: _,
+                } = &q.node
+                {
+                    self.init_corr.insert(
+                        CorrespondanceType::Key {
+                            node: k.node.clone(),
+                        },
+                        (k.node.clone(), k.span.clone()),
+                    );
+                    self.used_corr.push(UsedCorrespondence {
+                        ty: CorrespondanceType::Key {
+                            node: k.node.clone(),
+                        },
+                        key: key.node.clone(),
+                        span: k.span.clone(),
+                    });
+                }
+            }
+            StringExpr::Literal { value } => {
+                self.used_corr.push(UsedCorrespondence {
+                    ty: CorrespondanceType::Value {
+                        node: value.node.clone(),
+                    },
+                    key: key.node.clone(),
+                    span: value.span.clone(),
+                });
+            }
+            _ => {}
+        }
+    }
 }
 
 /// Gathers the information needed for the Semantic Check and Memory Check.
@@ -653,6 +692,21 @@ impl AstPass for SemanticVisitor {
                         }
                         _ => {}
                     },
+                    AggregateFilter::FollowKey { key, string } => {
+                        self.use_string_as_value_of(key, string);
+                    }
+                    _ => {}
+                },
+                NodeKind::AggregateCardPosition(a) => match a {
+                    AggregateCardPosition::TrickWinner { key, lead, .. } => {
+                        self.use_string_as_value_of(key, lead);
+                    }
+                    AggregateCardPosition::TrickWinnerTrump {
+                        trump, key, lead, ..
+                    } => {
+                        self.use_string_as_value_of(key, trump);
+                        self.use_string_as_value_of(key, lead);
+                    }
                     _ => {}
                 },
                 NodeKind::ActionRule(a) => match a {
@@ -683,6 +737,11 @@ impl AstPass for SemanticVisitor {
                         int: _,
                         memory,
                         players: _,
+                    }
+                    | ScoreRule::ScoreMemoryTeams {
+                        int: _,
+                        memory,
+                        teams: _,
                     } => {
                         self.memories
                             .push((memory.node.clone(), (MemType::Int, memory.span.clone())));
@@ -4,12 +4,13 @@
 ///    This allows better diagnostics and a nicer user experience.
 
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 pub type SID = Spanned<String>;
 pub type SInt = Spanned<i32>;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
 pub struct OwnedSpan {
     pub start: usize,
     pub end: usize,
@@ -3,6 +3,8 @@
 ///    then we give it the specific initialization Type (e.g. GameType::Player).
 ///    We can catch if a Symbol is never initialized and/or if it is defined
 ///    multiple times.
+///    Some usages need a specific Type (e.g. the token in `tokens Chips in Pot`).
+///    If such a Symbol is only initialized with other Types we report it as well.
 
 
 use std::collections::HashMap;
@@ -56,16 +58,20 @@ pub enum GameType {
 pub enum SymbolError {
     NotInitialized { var: Var },
     DefinedMultipleTimes { var: Var },
+    WrongType { var: Var, expected: GameType },
 }
 
 pub struct SymbolVisitor {
     symbols: HashMap<SID, GameType>,
+    /// Usages that need a specific Type
+    expected: Vec<(SID, GameType)>,
 }
 
 impl SymbolVisitor {
     pub fn new() -> Self {
         SymbolVisitor {
             symbols: HashMap::new(),
+            expected: Vec::new(),
         }
     }
 
@@ -73,6 +79,11 @@ impl SymbolVisitor {
         self.symbols.insert(id.clone(), GameType::NoType);
     }
 
+    fn use_id_as(&mut self, id: &SID, game_type: GameType) {
+        self.use_id(id);
+        self.expected.push((id.clone(), game_type));
+    }
+
     fn init_id(&mut self, id: &SID, game_type: GameType) {
         self.symbols.insert(id.clone(), game_type);
     }
@@ -128,11 +139,28 @@ impl SymbolVisitor {
             }
         }
 
+        // 5. Check usages that need a specific Type
+        // (never initialized is already reported above)
+        for (sid, expected) in self.expected.iter() {
+            let types: Vec<&GameType> = self
+                .symbols
+                .iter()
+                .filter(|(s, t)| s.node == sid.node && **t != GameType::NoType)
+                .map(|(_, t)| t)
+                .collect();
+            if !types.is_empty() && !types.contains(&expected) {
+                errs.push(SymbolError::WrongType {
+                    var: Var::from(sid.clone()),
+                    expected: expected.clone(),
+                });
+            }
+        }
+
         if errs.is_empty() {
             return None;
         }
 
-        // 5. Else return Ok
+        // 6. Else return Ok
         Some(errs)
     }
 
@@ -237,6 +265,12 @@ impl AstPass for SymbolVisitor {
                     } => {
                         self.use_id(&spanned2);
                     }
+                    AggregateInt::TokenCount {
+                        token: spanned,
+                        token_loc: _,
+                    } => {
+                        self.use_id_as(&spanned, GameType::Token);
+                    }
                     _ => {}
                 },
                 NodeKind::QueryString(q) => match q {
@@ -289,6 +323,15 @@ impl AstPass for SymbolVisitor {
                     } => {
                         self.use_id(&spanned2);
                     }
+                    AggregateCardPosition::TrickWinner {
+                        precedence, key, ..
+                    }
+                    | AggregateCardPosition::TrickWinnerTrump {
+                        precedence, key, ..
+                    } => {
+                        self.use_id_as(precedence, GameType::Precedence);
+                        self.use_id_as(key, GameType::Key);
+                    }
                 },
                 NodeKind::LocationCollection(l) => match l {
                     LocationCollection::Literal { locations } => {
@@ -355,6 +398,12 @@ impl AstPass for SymbolVisitor {
                     } => {
                         self.use_id(&spanned);
                     }
+                    AggregateFilter::FollowKey {
+                        key: spanned,
+                        string: _,
+                    } => {
+                        self.use_id_as(spanned, GameType::Key);
+                    }
                     AggregateFilter::Combo { combo: spanned } => {
                         self.use_id(&spanned);
                     }
@@ -465,7 +514,7 @@ impl AstPass for SymbolVisitor {
                         from_loc: _,
                         to_loc: _,
                     } => {
-                        self.use_id(&spanned);
+                        self.use_id_as(&spanned, GameType::Token);
                     }
                     TokenMove::PlaceQuantity {
                         quantity: _,
@@ -473,9 +522,17 @@ impl AstPass for SymbolVisitor {
                         from_loc: _,
                         to_loc: _,
                     } => {
-                        self.use_id(&spanned1);
+                        self.use_id_as(&spanned1, GameType::Token);
                     }
                 },
+                NodeKind::Stake(s) => match s {
+                    Stake::Tokens { token, from, to } => {
+                        self.use_id_as(token, GameType::Token);
+                        self.use_id_as(from, GameType::Location);
+                        self.use_id_as(to, GameType::Location);
+                    }
+                    Stake::Memory { memory } => self.use_id_as(memory, GameType::Memory),
+                },
                 NodeKind::ScoreRule(s) => match s {
                     ScoreRule::ScoreMemory {
                         int: _,
@@ -484,6 +541,13 @@ impl AstPass for SymbolVisitor {
                     } => {
                         self.use_id(&spanned1);
                     }
+                    ScoreRule::ScoreMemoryTeams {
+                        int: _,
+                        memory: spanned1,
+                        teams: _,
+                    } => {
+                        self.use_id_as(spanned1, GameType::Memory);
+                    }
                     _ => {}
                 },
                 NodeKind::WinnerType(w) => match w {
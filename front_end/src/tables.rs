@@ -0,0 +1,418 @@
+///    Runtime tables for precedences and pointmaps.
+///
+///    `precedence RankOrder on Rank(Two, ..., King, Ace)` orders Values from low
+///    to high, `points Values on Rank(Two: 2, ..., Ace: 11)` gives them points.
+///    A table can be defined on more than one Key
+///    (e.g. `precedence Trump (Suite Hearts, Rank Jack)`):
+///    - every card needs all Keys of the table (otherwise TableError::MissingKey),
+///    - at least one Value of the card has to be in the table
+///      (otherwise TableError::UnknownValue),
+///    - the rank of a card is the highest rank of its Values,
+///    - the points of a card are the sum of the points of its Values.
+///
+///    Ties are kept: `max of Hand using RankOrder` gives every card of the highest
+///    rank and the engine decides which one is meant.
+///
+///    `adjacent Rank using RankOrder` counts the Values of one Key. With wrap-around
+///    the last Value is followed by the first one again (King, Ace, Two is a run).
+
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::ast::*;
+use crate::filter_eval::Card;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TableError {
+    UnknownTable(String),
+    /// The card does not have a Key the table is defined on
+    MissingKey {
+        table: String,
+        key: String,
+        card: Card,
+    },
+    /// None of the Values of the card are in the table
+    UnknownValue {
+        table: String,
+        key: String,
+        value: String,
+    },
+    /// A point value that is not a constant (e.g. `Ace: &I:Memory`)
+    NeedsGameState(String),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::UnknownTable(table) => {
+                write!(f, "Unknown precedence or pointmap '{}'", table)
+            }
+            TableError::MissingKey { table, key, card } => write!(
+                f,
+                "Card {} does not have the key '{}' that '{}' is defined on",
+                card, key, table
+            ),
+            TableError::UnknownValue { table, key, value } => {
+                write!(
+                    f,
+                    "'{}' does not define '{}' for key '{}'",
+                    table, value, key
+                )
+            }
+            TableError::NeedsGameState(expr) => {
+                write!(f, "'{}' can only be evaluated in a running game", expr)
+            }
+        }
+    }
+}
+
+/// All precedences and pointmaps of a game.
+#[derive(Debug, Clone, Default)]
+pub struct Tables {
+    pub precedences: HashMap<String, PrecedenceTable>,
+    pub pointmaps: HashMap<String, PointMapTable>,
+}
+
+impl Tables {
+    pub fn from_game(game: &Game) -> Result<Self, TableError> {
+        let mut tables = Tables::default();
+        for setup in setup_rules(&game.flows) {
+            match setup {
+                SetUpRule::CreatePrecedence { precedence, kvs } => {
+                    tables
+                        .precedences
+                        .insert(precedence.clone(), PrecedenceTable::new(precedence, kvs));
+                }
+                SetUpRule::CreatePointMap { pointmap, kvis } => {
+                    tables
+                        .pointmaps
+                        .insert(pointmap.clone(), PointMapTable::new(pointmap, kvis)?);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(tables)
+    }
+
+    pub fn precedence(&self, precedence: &str) -> Result<&PrecedenceTable, TableError> {
+        self.precedences
+            .get(precedence)
+            .ok_or_else(|| TableError::UnknownTable(precedence.to_string()))
+    }
+
+    pub fn pointmap(&self, pointmap: &str) -> Result<&PointMapTable, TableError> {
+        self.pointmaps
+            .get(pointmap)
+            .ok_or_else(|| TableError::UnknownTable(pointmap.to_string()))
+    }
+}
+
+/// All SetUp-rules of the game (also the ones inside of stages).
+pub(crate) fn setup_rules(flows: &[FlowComponent]) -> Vec<&SetUpRule> {
+    let mut rules = Vec::new();
+    for flow in flows.iter() {
+        match flow {
+            FlowComponent::GameRule {
+                game_rule: GameRule::SetUp { setup },
+            } => rules.push(setup),
+            FlowComponent::GameRule { .. } | FlowComponent::BettingRound { .. } => {}
+            FlowComponent::SeqStage { stage } => rules.extend(setup_rules(&stage.flows)),
+            FlowComponent::SimStage { stage } => rules.extend(setup_rules(&stage.flows)),
+            FlowComponent::IfRule { if_rule } => rules.extend(setup_rules(&if_rule.flows)),
+            FlowComponent::ChoiceRule { choice_rule } => {
+                rules.extend(setup_rules(&choice_rule.options))
+            }
+            FlowComponent::OptionalRule { optional_rule } => {
+                rules.extend(setup_rules(&optional_rule.flows))
+            }
+            FlowComponent::TriggerRule { trigger_rule } => {
+                rules.extend(setup_rules(&trigger_rule.flows))
+            }
+            FlowComponent::Conditional { conditional } => {
+                for case in conditional.cases.iter() {
+                    match case {
+                        Case::NoBool { flows } => rules.extend(setup_rules(flows)),
+                        Case::Bool { flows, .. } => rules.extend(setup_rules(flows)),
+                    }
+                }
+            }
+        }
+    }
+
+    rules
+}
+
+/// IntExpr without game state (literals and arithmetic).
+pub(crate) fn static_int(int: &IntExpr) -> Result<i64, TableError> {
+    match int {
+        IntExpr::Literal { int } => Ok(*int as i64),
+        IntExpr::Binary { int, op, int1 } => {
+            let (left, right) = (static_int(int)?, static_int(int1)?);
+            // Division by zero gives 0
+            Ok(match op {
+                IntOp::Plus => left + right,
+                IntOp::Minus => left - right,
+                IntOp::Mul => left * right,
+                IntOp::Div => left.checked_div(right).unwrap_or(0),
+                IntOp::Mod => left.checked_rem(right).unwrap_or(0),
+            })
+        }
+        _ => Err(TableError::NeedsGameState(int.to_string())),
+    }
+}
+
+/// The Keys of a table in the order they first appear.
+fn keys_of<'a>(keys: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for key in keys {
+        if !unique.contains(key) {
+            unique.push(key.clone());
+        }
+    }
+
+    unique
+}
+
+/// Returns every card with the extreme score (all of them on a tie).
+fn extrema_by<'a, S, F>(
+    extrema: &Extrema,
+    cards: &[&'a Card],
+    score: F,
+) -> Result<Vec<&'a Card>, TableError>
+where
+    S: Ord + Copy,
+    F: Fn(&Card) -> Result<S, TableError>,
+{
+    let scores = cards
+        .iter()
+        .map(|card| score(card))
+        .collect::<Result<Vec<_>, _>>()?;
+    let best = match extrema {
+        Extrema::Min => scores.iter().min(),
+        Extrema::Max => scores.iter().max(),
+    };
+
+    Ok(cards
+        .iter()
+        .zip(scores.iter())
+        .filter(|(_, score)| Some(*score) == best)
+        .map(|(card, _)| *card)
+        .collect())
+}
+
+// ===========================================================================
+// Precedence
+// ===========================================================================
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrecedenceTable {
+    pub name: String,
+    /// From low to high
+    pub kvs: Vec<(String, String)>,
+    keys: Vec<String>,
+    /// `adjacent` goes from the highest Value back to the lowest
+    pub wrap_around: bool,
+}
+
+impl PrecedenceTable {
+    pub fn new(name: &str, kvs: &[(String, String)]) -> Self {
+        PrecedenceTable {
+            name: name.to_string(),
+            kvs: kvs.to_vec(),
+            keys: keys_of(kvs.iter().map(|(key, _)| key)),
+            wrap_around: false,
+        }
+    }
+
+    pub fn with_wrap_around(mut self, wrap_around: bool) -> Self {
+        self.wrap_around = wrap_around;
+        self
+    }
+
+    /// Rank of a Value in the whole table.
+    pub fn value_rank(&self, key: &str, value: &str) -> Result<usize, TableError> {
+        self.kvs
+            .iter()
+            .position(|(k, v)| k == key && v == value)
+            .ok_or_else(|| self.unknown_value(key, value))
+    }
+
+    /// Rank of a card (the highest rank of its Values).
+    pub fn rank(&self, card: &Card) -> Result<usize, TableError> {
+        let mut rank = None;
+        for key in self.keys.iter() {
+            let value = self.value(key, card)?;
+            if let Ok(r) = self.value_rank(key, value) {
+                rank = rank.max(Some(r));
+            }
+        }
+
+        rank.ok_or_else(|| {
+            let key = &self.keys[0];
+            self.unknown_value(key, card.get(key).unwrap_or_default())
+        })
+    }
+
+    /// Rank of the card by one Key (e.g. `Rank higher than "Ten" using RankOrder`).
+    pub fn key_rank(&self, key: &str, card: &Card) -> Result<usize, TableError> {
+        self.value_rank(key, self.value(key, card)?)
+    }
+
+    /// Equal ranks are a tie.
+    pub fn compare(&self, card: &Card, other: &Card) -> Result<Ordering, TableError> {
+        Ok(self.rank(card)?.cmp(&self.rank(other)?))
+    }
+
+    pub fn higher_than(&self, card: &Card, key: &str, value: &str) -> Result<bool, TableError> {
+        Ok(self.key_rank(key, card)? > self.value_rank(key, value)?)
+    }
+
+    pub fn lower_than(&self, card: &Card, key: &str, value: &str) -> Result<bool, TableError> {
+        Ok(self.key_rank(key, card)? < self.value_rank(key, value)?)
+    }
+
+    /// `max/min of ... using Precedence`
+    pub fn extrema<'a>(
+        &self,
+        extrema: &Extrema,
+        cards: &[&'a Card],
+    ) -> Result<Vec<&'a Card>, TableError> {
+        extrema_by(extrema, cards, |card| self.rank(card))
+    }
+
+    /// Do the Values of the Key follow each other without gaps or duplicates?
+    pub fn adjacent(&self, key: &str, cards: &[&Card]) -> Result<bool, TableError> {
+        let values: Vec<&String> = self
+            .kvs
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .collect();
+        let mut indices = cards
+            .iter()
+            .map(|card| {
+                let value = self.value(key, card)?;
+                values
+                    .iter()
+                    .position(|v| *v == value)
+                    .ok_or_else(|| self.unknown_value(key, value))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        indices.sort();
+        if indices.windows(2).any(|w| w[0] == w[1]) {
+            return Ok(false);
+        }
+
+        let gaps = indices.windows(2).filter(|w| w[0] + 1 != w[1]).count();
+        match (indices.first(), indices.last()) {
+            (Some(first), Some(last)) if self.wrap_around => {
+                // The gap between the highest and the lowest Value
+                let wrap_gap = first + values.len() - last != 1;
+                Ok(gaps + wrap_gap as usize <= 1)
+            }
+            _ => Ok(gaps == 0),
+        }
+    }
+
+    fn value<'a>(&self, key: &str, card: &'a Card) -> Result<&'a str, TableError> {
+        card.get(key).ok_or_else(|| TableError::MissingKey {
+            table: self.name.clone(),
+            key: key.to_string(),
+            card: card.clone(),
+        })
+    }
+
+    fn unknown_value(&self, key: &str, value: &str) -> TableError {
+        TableError::UnknownValue {
+            table: self.name.clone(),
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+// ===========================================================================
+// PointMap
+// ===========================================================================
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointMapTable {
+    pub name: String,
+    pub points: Vec<(String, String, i64)>,
+    keys: Vec<String>,
+}
+
+impl PointMapTable {
+    pub fn new(name: &str, kvis: &[(String, String, IntExpr)]) -> Result<Self, TableError> {
+        let points = kvis
+            .iter()
+            .map(|(key, value, int)| Ok((key.clone(), value.clone(), static_int(int)?)))
+            .collect::<Result<Vec<_>, TableError>>()?;
+
+        Ok(PointMapTable {
+            name: name.to_string(),
+            keys: keys_of(points.iter().map(|(key, _, _)| key)),
+            points,
+        })
+    }
+
+    /// Points of a card (the sum of the points of its Values).
+    pub fn card_points(&self, card: &Card) -> Result<i64, TableError> {
+        let mut points = None;
+        for key in self.keys.iter() {
+            let value = card.get(key).ok_or_else(|| TableError::MissingKey {
+                table: self.name.clone(),
+                key: key.clone(),
+                card: card.clone(),
+            })?;
+            for (_, _, p) in self
+                .points
+                .iter()
+                .filter(|(k, v, _)| k == key && v == value)
+            {
+                points = Some(points.unwrap_or(0) + p);
+            }
+        }
+
+        points.ok_or_else(|| {
+            let key = &self.keys[0];
+            TableError::UnknownValue {
+                table: self.name.clone(),
+                key: key.clone(),
+                value: card.get(key).unwrap_or_default().to_string(),
+            }
+        })
+    }
+
+    /// `sum of ... using PointMap`
+    pub fn sum(&self, cards: &[&Card]) -> Result<i64, TableError> {
+        cards.iter().map(|card| self.card_points(card)).sum()
+    }
+
+    /// `max/min of ... using points PointMap` (the cards)
+    pub fn extrema<'a>(
+        &self,
+        extrema: &Extrema,
+        cards: &[&'a Card],
+    ) -> Result<Vec<&'a Card>, TableError> {
+        extrema_by(extrema, cards, |card| self.card_points(card))
+    }
+
+    /// `max/min of ... using PointMap` (the points, None for no cards)
+    pub fn extrema_points(
+        &self,
+        extrema: &Extrema,
+        cards: &[&Card],
+    ) -> Result<Option<i64>, TableError> {
+        let points = cards
+            .iter()
+            .map(|card| self.card_points(card))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(match extrema {
+            Extrema::Min => points.into_iter().min(),
+            Extrema::Max => points.into_iter().max(),
+        })
+    }
+}
@@ -0,0 +1,291 @@
+///    Termination analysis for stages.
+///
+///    Every stage is a loop in the IR that is closed by a StageRoundCounter-edge.
+///    A stage like `stage Collect for current until previous out of stage` loops
+///    forever if nothing in its body ever sets a player out.
+///
+///    For every loop we compare what the EndCondition reads with what the
+///    edges inside of the loop write (see Aspect). The loop can end if
+///    - the EndCondition counts repetitions (`3 times`),
+///    - an edge leaves the loop (e.g. `end stage`, `end game`) or
+///    - an edge inside of the loop writes something the EndCondition reads.
+///
+///    This is an over-approximation: a warning means that the stage can not end,
+///    no warning does not mean that the stage ends.
+
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::ast::ast_spanned::*;
+use crate::ir::{BetStep, Ir, Payload, SpannedPayload, StateID};
+use crate::spans::{OwnedSpan, SID};
+use crate::walker::{AstPass, Walker};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerminationWarning {
+    /// Nothing in the stage can make the EndCondition true.
+    EndConditionUnreachable { stage: String, span: OwnedSpan },
+    /// `until end` without an `end` that leaves the stage.
+    NoEndAction { stage: String, span: OwnedSpan },
+}
+
+impl fmt::Display for TerminationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminationWarning::EndConditionUnreachable { stage, .. } => write!(
+                f,
+                "Stage '{}' may never end: nothing in the stage can make the end condition true",
+                stage
+            ),
+            TerminationWarning::NoEndAction { stage, .. } => write!(
+                f,
+                "Stage '{}' never ends: it runs 'until end' but nothing in it ends the stage or the game",
+                stage
+            ),
+        }
+    }
+}
+
+impl TerminationWarning {
+    pub fn span(&self) -> &OwnedSpan {
+        match self {
+            TerminationWarning::EndConditionUnreachable { span, .. } => span,
+            TerminationWarning::NoEndAction { span, .. } => span,
+        }
+    }
+}
+
+/// The parts of the game state an EndCondition can depend on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Aspect {
+    /// Players that are out of a stage or the game
+    Out,
+    Memory(String),
+    /// Cards and tokens on locations
+    Locations,
+    /// current, next, previous, ...
+    Turn,
+    /// Always written by the loop itself
+    RoundCounter,
+}
+
+pub fn termination_analysis(fsm: &Ir<SpannedPayload>) -> Vec<TerminationWarning> {
+    let mut predecessors: HashMap<StateID, Vec<StateID>> = HashMap::new();
+    for (from, edges) in fsm.states.iter() {
+        for edge in edges.iter() {
+            predecessors.entry(edge.to).or_default().push(*from);
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for (from, edges) in fsm.states.iter() {
+        for edge in edges.iter() {
+            if let Payload::StageRoundCounter(stage) = &edge.payload
+                && let Some(warning) = check_loop(fsm, &predecessors, stage, *from, edge.to)
+            {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Checks the loop that is closed by the StageRoundCounter-edge `back_edge -> entry`.
+fn check_loop(
+    fsm: &Ir<SpannedPayload>,
+    predecessors: &HashMap<StateID, Vec<StateID>>,
+    stage: &SID,
+    back_edge: StateID,
+    entry: StateID,
+) -> Option<TerminationWarning> {
+    // Natural loop: everything that reaches the back_edge without passing the entry
+    let mut body: HashSet<StateID> = HashSet::from([entry, back_edge]);
+    let mut stack = vec![back_edge];
+    while let Some(state) = stack.pop() {
+        if state == entry {
+            continue;
+        }
+        for pred in predecessors.get(&state).into_iter().flatten() {
+            if body.insert(*pred) {
+                stack.push(*pred);
+            }
+        }
+    }
+
+    let mut end_condition = None;
+    let mut writes: HashSet<Aspect> = HashSet::from([Aspect::RoundCounter]);
+    for state in body.iter() {
+        for edge in fsm.states.get(state).into_iter().flatten() {
+            match &edge.payload {
+                Payload::EndCondition {
+                    expr,
+                    negated: true,
+                    stage: s,
+                } if *state == entry && s.node == stage.node => {
+                    end_condition = Some(expr);
+                    continue;
+                }
+                Payload::Action(rule) => writes.extend(rule_writes(rule)),
+                Payload::Bet { round, step } => writes.extend(bet_writes(round, *step)),
+                _ => {}
+            }
+
+            // e.g. `end stage` or `end game`
+            if !body.contains(&edge.to) {
+                return None;
+            }
+        }
+    }
+
+    let Some(end_condition) = end_condition else {
+        return Some(TerminationWarning::NoEndAction {
+            stage: stage.node.clone(),
+            span: stage.span.clone(),
+        });
+    };
+
+    let condition = match &end_condition.node {
+        EndCondition::UntilBool { bool_expr } => bool_expr,
+        EndCondition::UntilBoolRep {
+            bool_expr, logic, ..
+        } => match logic.node {
+            BoolOp::And => bool_expr,
+            BoolOp::Or => return None,
+        },
+        EndCondition::UntilRep { .. } | EndCondition::UntilEnd => return None,
+    };
+
+    let mut reads = Reads::default();
+    condition.walk(&mut reads);
+
+    // A condition that reads nothing we know about is left alone
+    if reads.aspects.is_empty() || !reads.aspects.is_disjoint(&writes) {
+        return None;
+    }
+
+    Some(TerminationWarning::EndConditionUnreachable {
+        stage: stage.node.clone(),
+        span: end_condition.span.clone(),
+    })
+}
+
+// ===========================================================================
+// Reads and Writes
+// ===========================================================================
+/// `previous out of stage` only becomes true if somebody is set out in the stage.
+/// Which player is meant (previous, current, ...) does not matter, so the players
+/// of an out-of-stage check are skipped (skip > 0).
+#[derive(Default)]
+struct Reads {
+    aspects: HashSet<Aspect>,
+    skip: usize,
+}
+
+fn is_out_of_stage<T: Walker>(node: &T) -> bool {
+    matches!(
+        node.kind(),
+        Some(NodeKind::AggregateBool(AggregateBool::OutOfPlayer { out_of, .. }))
+            if matches!(out_of.node, OutOf::CurrentStage | OutOf::Stage { .. })
+    )
+}
+
+impl AstPass for Reads {
+    fn enter_node<T: Walker>(&mut self, node: &T)
+    where
+        Self: Sized,
+    {
+        if is_out_of_stage(node) {
+            self.aspects.insert(Aspect::Out);
+            self.skip += 1;
+        }
+        if self.skip > 0 {
+            return;
+        }
+
+        let aspect = match node.kind() {
+            Some(NodeKind::AggregateBool(AggregateBool::OutOfPlayer { .. })) => Aspect::Out,
+            Some(NodeKind::RuntimePlayerCollection(
+                RuntimePlayerCollection::PlayersIn | RuntimePlayerCollection::PlayersOut,
+            )) => Aspect::Out,
+            Some(NodeKind::UseMemory(UseMemory::Memory { memory }))
+            | Some(NodeKind::UseMemory(UseMemory::WithOwner { memory, .. }))
+            | Some(NodeKind::UseSingleMemory(UseSingleMemory::Memory { memory }))
+            | Some(NodeKind::UseSingleMemory(UseSingleMemory::WithOwner { memory, .. }))
+            | Some(NodeKind::AggregatePlayer(AggregatePlayer::OwnerOfMemory { memory, .. })) => {
+                Aspect::Memory(memory.node.clone())
+            }
+            Some(NodeKind::CardSet(_)) | Some(NodeKind::Groupable(_)) => Aspect::Locations,
+            Some(NodeKind::RuntimePlayer(_)) => Aspect::Turn,
+            Some(NodeKind::RuntimeInt(_)) => Aspect::RoundCounter,
+            _ => return,
+        };
+
+        self.aspects.insert(aspect);
+    }
+
+    fn exit_node<T: Walker>(&mut self, node: &T)
+    where
+        Self: Sized,
+    {
+        if is_out_of_stage(node) {
+            self.skip -= 1;
+        }
+    }
+}
+
+fn rule_writes(rule: &SGameRule) -> Vec<Aspect> {
+    match &rule.node {
+        GameRule::Action { action } => match &action.node {
+            ActionRule::FlipAction { .. }
+            | ActionRule::ShuffleAction { .. }
+            | ActionRule::Move { .. } => vec![Aspect::Locations],
+            ActionRule::OutAction { .. } => vec![Aspect::Out],
+            ActionRule::SetMemory { memory, .. }
+            | ActionRule::ResetMemory { memory }
+            | ActionRule::BidMemoryAction { memory, .. }
+            | ActionRule::DemandMemoryAction { memory, .. } => {
+                vec![Aspect::Memory(memory.node.clone())]
+            }
+            ActionRule::CycleAction { .. } => vec![Aspect::Turn],
+            ActionRule::BidAction { .. }
+            | ActionRule::DemandAction { .. }
+            | ActionRule::EndAction { .. } => vec![],
+        },
+        GameRule::Scoring { scoring } => match &scoring.node {
+            ScoringRule::ScoreRule { score_rule } => match &score_rule.node {
+                ScoreRule::ScoreMemory { memory, .. }
+                | ScoreRule::ScoreMemoryTeams { memory, .. } => {
+                    vec![Aspect::Memory(memory.node.clone())]
+                }
+                ScoreRule::Score { .. } | ScoreRule::ScoreTeams { .. } => vec![],
+            },
+            ScoringRule::WinnerRule { .. } => vec![],
+        },
+        GameRule::SetUp { setup } => match &setup.node {
+            SetUpRule::CreateMemory { memory, .. }
+            | SetUpRule::CreateMemoryWithMemoryType { memory, .. } => {
+                vec![Aspect::Memory(memory.node.clone())]
+            }
+            SetUpRule::CreateCardOnLocation { .. } | SetUpRule::CreateTokenOnLocation { .. } => {
+                vec![Aspect::Locations]
+            }
+            _ => vec![],
+        },
+    }
+}
+
+fn bet_writes(round: &SBettingRound, step: BetStep) -> Vec<Aspect> {
+    let stake = match &round.node {
+        BettingRound::Bet { stake, .. } | BettingRound::BetMinimum { stake, .. } => stake,
+    };
+    match step {
+        BetStep::Fold => vec![Aspect::Out],
+        BetStep::Call | BetStep::Raise => match &stake.node {
+            Stake::Tokens { .. } => vec![Aspect::Locations],
+            Stake::Memory { memory } => vec![Aspect::Memory(memory.node.clone())],
+        },
+        BetStep::Settled | BetStep::Open | BetStep::NextPlayer => vec![],
+    }
+}
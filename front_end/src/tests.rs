@@ -11,14 +11,29 @@
 /// This way we can check for mistakes in the AST-declaration and further Parsing errors
 /// by doing: Generate AST -> String-Represenation -> Parse -> assert_eq Generated-AST and Parse-Output
 
+use std::cmp::Ordering;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
 
+use crate::card_count::{CardCountWarning, card_count_analysis};
+use crate::diff::{Change, diff_games};
+use crate::filter_eval::{Card, FilterError, FilterEvaluator, cards_of};
 use crate::fsm_to_dot::fsm_to_dot;
-use crate::ir::{Ir, IrBuilder, SpannedPayload};
+use crate::information_flow::{InformationLeak, information_flow_analysis};
+use crate::ir::{BetStep, Ir, IrBuilder, IrStructureError, Payload, SpannedPayload};
+use crate::ir_loader::{IrFormat, IrLoadError, load_ir_from_bytes};
+use crate::ir_passes::{IrPass, IrSize, optimize};
 use crate::lower::Lower;
 use crate::parser::{CGDSLParser, Node, Result, Rule};
+use crate::schema::{
+    IR_FORMAT_VERSION, IrEnvelope, SCHEMA_DIR, ir_schema_json, schema_file_name, source_hash,
+};
+use crate::semantic::SemanticError;
+use crate::symbols::{GameType, SymbolError};
+use crate::tables::{TableError, Tables};
+use crate::termination::{TerminationWarning, termination_analysis};
+use crate::validation::{semantic_validation, symbol_validation};
 use crate::walker::*;
 use pest_consume::*;
 
@@ -294,6 +309,1345 @@ fn test_game_ir() {
     show_graph(&fsm, "game");
 }
 
+#[test]
+fn test_trigger_ir() {
+    let input = "
+      location Hand on all
+      location Pile on table
+      stage Grab for all until Pile empty {
+        trigger {
+          move top(Pile) private to Hand
+        }
+      }
+      trigger {
+        end game with winner current
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    let fsm = game.to_lowered_graph();
+
+    let triggers: Vec<_> = fsm
+        .states
+        .iter()
+        .flat_map(|(from, edges)| edges.iter().map(move |edge| (*from, edge)))
+        .filter_map(|(from, edge)| match &edge.payload {
+            Payload::Trigger {
+                players,
+                stage,
+                claimed,
+            } => Some((from, edge.to, players.to_string(), stage.clone(), *claimed)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(triggers.len(), 4);
+
+    // The claimant executes the body, everybody else skips it
+    for stage in [Some("Grab".to_string()), None] {
+        let [claim, lost] = [true, false].map(|claimed| {
+            triggers
+                .iter()
+                .find(|t| t.3 == stage && t.4 == claimed)
+                .expect("missing trigger edge")
+        });
+        assert_eq!(claim.0, lost.0);
+        assert_ne!(claim.1, lost.1);
+        assert_eq!(claim.2, "all");
+    }
+
+    assert!(fsm.structural_errors().is_empty());
+}
+
+#[test]
+fn test_source_map() {
+    let input = "
+      stage Collect for current until Hand empty {
+        optional {
+          set current out of stage
+        }
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    let (fsm, source_map) = game.to_lowered_graph_with_source_map();
+
+    // Every edge of the lowered IR has an origin
+    let edge_count: usize = fsm.states.values().map(|edges| edges.len()).sum();
+    assert_eq!(edge_count, source_map.edges.len());
+
+    for entry in source_map.edges.iter() {
+        let edge = fsm.edge(entry.edge).expect("edge of source map not in IR");
+        assert_eq!(edge.to, entry.to);
+
+        let text = &input[entry.span.start..entry.span.end];
+        match &edge.payload {
+            Payload::Action(_) => assert_eq!(text, "set current out of stage"),
+            Payload::EndCondition { .. } => assert_eq!(text, "until Hand empty"),
+            Payload::StageRoundCounter(_) => assert_eq!(text, "Collect"),
+            Payload::Optional => assert!(text.starts_with("optional")),
+            _ => {}
+        }
+    }
+}
+
+// ===========================================================================
+// Test IR format
+// ===========================================================================
+/// Fails if the JSON Schema of the lowered IR changed but IR_FORMAT_VERSION was not bumped.
+/// After bumping the version run the tests with CGDSL_UPDATE_SCHEMA=1 to write the
+/// new schema (existing schemas are never overwritten).
+#[test]
+fn test_ir_schema_compatibility() {
+    let schema_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(SCHEMA_DIR);
+    let path = schema_dir.join(schema_file_name(IR_FORMAT_VERSION));
+    let schema = ir_schema_json();
+
+    if !path.exists() && std::env::var("CGDSL_UPDATE_SCHEMA").is_ok() {
+        fs::create_dir_all(&schema_dir).expect("Failed to create schema folder");
+        fs::write(&path, &schema).expect("Failed to write schema");
+    }
+
+    let committed = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "No schema for format_version {} (run with CGDSL_UPDATE_SCHEMA=1)",
+            IR_FORMAT_VERSION
+        )
+    });
+
+    assert!(
+        committed == schema,
+        "The JSON format of the lowered IR changed: bump IR_FORMAT_VERSION in schema.rs \
+         and run with CGDSL_UPDATE_SCHEMA=1"
+    );
+}
+
+#[test]
+fn test_ir_envelope() {
+    let input = "
+      stage Collect for current until Hand empty {
+        set current out of stage
+      }
+    ";
+    let reformatted = "stage Collect for current until Hand empty { set current out of stage }";
+
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    let other =
+        test_rule_consume(reformatted, Rule::file, CGDSLParser::file).expect("parse failed");
+
+    let envelope = IrEnvelope::from_game("Collect", &game);
+    assert_eq!(IR_FORMAT_VERSION, envelope.format_version);
+    assert_eq!(source_hash(&other), envelope.source_hash);
+
+    let json = serde_json::to_string(&envelope).expect("Serialization failed");
+    let parsed: IrEnvelope = serde_json::from_str(&json).expect("Deserialization failed");
+    assert_eq!(envelope.ir.states.len(), parsed.ir.states.len());
+}
+
+#[test]
+fn test_load_ir_formats() {
+    let input = "
+      stage Collect for current until Hand empty {
+        choose {
+          move top(Discard) private to Hand
+          or
+          move top(Stock) private to Hand
+        }
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    let envelope = IrEnvelope::from_game("Collect", &game);
+
+    let json = serde_json::to_vec(&envelope).unwrap();
+    let ron = ron::ser::to_string(&envelope).unwrap().into_bytes();
+    let bincode = bincode::serialize(&envelope).unwrap();
+
+    for (bytes, format) in [
+        (json, IrFormat::Json),
+        (ron, IrFormat::Ron),
+        (bincode, IrFormat::Bincode),
+    ] {
+        let loaded = load_ir_from_bytes(&bytes, format).expect("loading failed");
+        assert_eq!(envelope.source_hash, loaded.source_hash);
+        assert_eq!(envelope.ir.states.len(), loaded.ir.states.len());
+    }
+}
+
+#[test]
+fn test_load_ir_validation() {
+    let game = test_rule_consume("set current out of stage", Rule::file, CGDSLParser::file)
+        .expect("parse failed");
+    let envelope = IrEnvelope::from_game("Out", &game);
+    let valid = serde_json::to_value(&envelope).unwrap();
+
+    // Hand-edited: edge goes to a state that does not exist and an unreachable state
+    let mut edited = valid.clone();
+    edited["ir"]["states"]["0"][0]["to"] = serde_json::json!(42);
+    let bytes = serde_json::to_vec(&edited).unwrap();
+    match load_ir_from_bytes(&bytes, IrFormat::Json) {
+        Err(IrLoadError::Structure(errs)) => {
+            assert!(matches!(errs[0], IrStructureError::DanglingEdge { .. }));
+            assert!(matches!(errs[1], IrStructureError::UnreachableState { .. }));
+        }
+        other => panic!("expected structural errors, got {:?}", other),
+    }
+
+    // Missing entry
+    let mut edited = valid.clone();
+    edited["ir"]["entry"] = serde_json::json!(7);
+    let bytes = serde_json::to_vec(&edited).unwrap();
+    match load_ir_from_bytes(&bytes, IrFormat::Json) {
+        Err(IrLoadError::Structure(errs)) => {
+            assert!(matches!(errs[0], IrStructureError::MissingEntry { .. }));
+        }
+        other => panic!("expected structural errors, got {:?}", other),
+    }
+
+    // Old format
+    let mut edited = valid.clone();
+    edited["format_version"] = serde_json::json!(0);
+    let bytes = serde_json::to_vec(&edited).unwrap();
+    assert!(matches!(
+        load_ir_from_bytes(&bytes, IrFormat::Json),
+        Err(IrLoadError::FormatVersion { .. })
+    ));
+
+    // A version 1 file: a Trigger had no fields back then
+    let input = "
+      stage Grab for all until Pile empty {
+        trigger {
+          move top(Pile) private to Hand
+        }
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    let mut v1 = serde_json::to_value(IrEnvelope::from_game("Grab", &game)).unwrap();
+    v1["format_version"] = serde_json::json!(1);
+    for edges in v1["ir"]["states"].as_object_mut().unwrap().values_mut() {
+        for edge in edges.as_array_mut().unwrap() {
+            if edge["payload"].get("Trigger").is_some() {
+                edge["payload"] = serde_json::json!("Trigger");
+            }
+        }
+    }
+    let bytes = serde_json::to_vec(&v1).unwrap();
+    assert!(serde_json::from_slice::<IrEnvelope>(&bytes).is_err());
+    assert!(matches!(
+        load_ir_from_bytes(&bytes, IrFormat::Json),
+        Err(IrLoadError::FormatVersion { found: 1, .. })
+    ));
+
+    // bincode only needs the leading format_version
+    let mut bytes = 1u32.to_le_bytes().to_vec();
+    bytes.extend([0xff; 3]);
+    assert!(matches!(
+        load_ir_from_bytes(&bytes, IrFormat::Bincode),
+        Err(IrLoadError::FormatVersion { found: 1, .. })
+    ));
+}
+
+#[test]
+fn test_stable_state_ids() {
+    let setup = "
+      stage Setup for current 1 times {
+        optional {
+          set current out of stage
+        }
+      }
+    ";
+    // Unrelated edit in stage Setup
+    let edited_setup = "
+      stage Setup for current 1 times {
+        set current out of stage
+        optional {
+          set current out of stage
+        }
+      }
+    ";
+    let play = "
+      stage Play for current until Hand empty {
+        if (Hand empty) {
+          set current out of stage
+        }
+      }
+    ";
+
+    // (text, EdgeID, target) of every edge in stage Play
+    let play_edges = |setup: &str| {
+        let input = format!("{}{}", setup, play);
+        let game = test_rule_consume(&input, Rule::file, CGDSLParser::file).expect("parse failed");
+        let (fsm, source_map) = game.to_lowered_graph_with_source_map();
+
+        // Same game gives the same JSON
+        let json = serde_json::to_string(&fsm).unwrap();
+        assert_eq!(
+            json,
+            serde_json::to_string(&game.to_lowered_graph()).unwrap()
+        );
+
+        source_map
+            .edges
+            .iter()
+            .filter(|e| e.span.start >= setup.len())
+            .map(|e| (input[e.span.start..e.span.end].to_string(), e.edge, e.to))
+            .collect::<Vec<_>>()
+    };
+
+    let before = play_edges(setup);
+    let after = play_edges(edited_setup);
+
+    assert!(!before.is_empty());
+    assert_eq!(before, after);
+
+    // Rules are counted by position: a rule inserted in front of another
+    // one renames its states (the stage after it keeps its ids)
+    let edges_of = |play: &str| {
+        let input = format!("{}{}", play, edited_setup);
+        let game = test_rule_consume(&input, Rule::file, CGDSLParser::file).expect("parse failed");
+        let (_, source_map) = game.to_lowered_graph_with_source_map();
+        let edges = |text: &str| {
+            source_map
+                .edges
+                .iter()
+                .filter(|e| &input[e.span.start..e.span.end] == text)
+                .map(|e| (e.edge.from, e.to))
+                .collect::<Vec<_>>()
+        };
+
+        (edges("cycle to next"), edges("set current out of stage"))
+    };
+    let (cycle, setup_edges) = edges_of(
+        "
+      stage Play for current until Hand empty {
+        move top(Stock) private to Hand
+        cycle to next
+      }
+    ",
+    );
+    let (inserted, inserted_setup_edges) = edges_of(
+        "
+      stage Play for current until Hand empty {
+        cycle to previous
+        move top(Stock) private to Hand
+        cycle to next
+      }
+    ",
+    );
+    assert_ne!(cycle, inserted);
+    assert_eq!(setup_edges, inserted_setup_edges);
+}
+
+// ===========================================================================
+// Test termination analysis
+// ===========================================================================
+#[test]
+fn test_termination_analysis() {
+    let warnings = |input: &str| {
+        let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+        termination_analysis(&game.to_graph())
+    };
+
+    // Nobody is ever set out
+    let input = "
+      stage Collect for current until previous out of stage {
+        move top(Stock) private to Hand
+        cycle to next
+      }
+    ";
+    match &warnings(input)[..] {
+        [TerminationWarning::EndConditionUnreachable { stage, span }] => {
+            assert_eq!(stage, "Collect");
+            assert_eq!(&input[span.start..span.end], "until previous out of stage");
+        }
+        other => panic!("expected one warning, got {:?}", other),
+    }
+
+    let input = "
+      stage Collect for current until previous out of stage {
+        move top(Stock) private to Hand
+        if (Hand empty) {
+          set current out of stage
+        }
+        cycle to next
+      }
+    ";
+    assert!(warnings(input).is_empty());
+
+    // until end needs an end
+    let input = "
+      stage Loop for current until end {
+        cycle to next
+      }
+    ";
+    assert!(matches!(
+        warnings(input)[..],
+        [TerminationWarning::NoEndAction { .. }]
+    ));
+
+    let input = "
+      stage Loop for current until end {
+        if (Hand empty) {
+          end stage
+        }
+        cycle to next
+      }
+    ";
+    assert!(warnings(input).is_empty());
+
+    // Repetitions always end
+    let input = "
+      stage Once for current 1 times {
+        cycle to next
+      }
+    ";
+    assert!(warnings(input).is_empty());
+}
+
+// ===========================================================================
+// Test card count analysis
+// ===========================================================================
+#[test]
+fn test_card_count_analysis() {
+    let warnings = |input: &str| {
+        let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+        card_count_analysis(&game.to_graph())
+    };
+
+    // 4 * 12 cards are needed, but there are only 13 * 3
+    let input = "
+      player P1, P2, P3, P4
+      location Hand on all
+      location Stock on table
+      card on Stock:
+        Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace)
+          for Suite(Diamonds, Hearts, Spades)
+
+      stage Deal for current 1 times {
+        deal 12 from top(Stock) private to Hand of all
+      }
+    ";
+    match &warnings(input)[..] {
+        [
+            CardCountWarning::NotEnoughCards {
+                location,
+                needed,
+                available,
+                span,
+            },
+        ] => {
+            assert_eq!(location, "Stock");
+            assert_eq!(*needed, 48);
+            assert_eq!(*available, 39);
+            assert_eq!(&input[span.start..span.end], "top(Stock)");
+        }
+        other => panic!("expected one warning, got {:?}", other),
+    }
+
+    // With a full deck the stage deals only once
+    let input = input.replace(
+        "for Suite(Diamonds, Hearts, Spades)",
+        "for Suite(Diamonds, Hearts, Spades, Clubs)",
+    );
+    assert!(warnings(&input).is_empty());
+
+    // Discard is empty in the first round and Stock runs out eventually
+    let input = "
+      player P1, P2, P3
+      location Hand on all
+      location Stock, Discard on table
+      card on Stock:
+        Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace)
+          for Suite(Diamonds, Hearts, Spades, Clubs)
+
+      stage Collect for current until previous out of stage {
+        choose {
+          move top(Discard) private to Hand
+          or
+          move top(Stock) private to Hand
+        }
+        move any from Hand face up to top(Discard)
+        if (Hand empty) {
+          set current out of stage
+        }
+        cycle to next
+      }
+    ";
+    let mut locations: Vec<String> = warnings(input)
+        .into_iter()
+        .map(|w| match w {
+            CardCountWarning::MaybeNotEnoughCards {
+                location, needed, ..
+            } => {
+                assert_eq!(needed, 1);
+                location
+            }
+            other => panic!("expected MaybeNotEnoughCards, got {:?}", other),
+        })
+        .collect();
+    locations.sort();
+    assert_eq!(locations, vec!["Discard", "Stock"]);
+
+    // Emptying the Hand of the current player leaves the other Hands alone
+    let input = "
+      player P1, P2, P3
+      location Hand on all
+      location Stock, Trash on table
+      card on Stock:
+        Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace)
+          for Suite(Diamonds, Hearts, Spades, Clubs)
+
+      stage Play for current 1 times {
+        deal 3 from top(Stock) private to Hand of all
+        move Hand face up to Trash
+        move top(Hand) of next face up to top(Trash)
+      }
+    ";
+    match &warnings(input)[..] {
+        [CardCountWarning::MaybeNotEnoughCards { location, span, .. }] => {
+            assert_eq!(location, "Hand");
+            assert_eq!(&input[span.start..span.end], "top(Hand) of next");
+        }
+        other => panic!("expected one warning, got {:?}", other),
+    }
+
+    // A card for the current player is not a card for the next one
+    let input = input.replace(
+        "deal 3 from top(Stock) private to Hand of all\n        move Hand face up to Trash",
+        "move top(Stock) private to Hand\n        move top(Hand) face up to Trash\n        move top(Stock) private to Hand",
+    );
+    match &warnings(&input)[..] {
+        [CardCountWarning::MaybeNotEnoughCards { span, .. }] => {
+            assert_eq!(&input[span.start..span.end], "top(Hand) of next");
+        }
+        other => panic!("expected one warning, got {:?}", other),
+    }
+}
+
+// ===========================================================================
+// Test information flow analysis
+// ===========================================================================
+#[test]
+fn test_information_flow_analysis() {
+    let leaks = |rules: &str| {
+        let input = format!(
+            "
+      player P1, P2, P3
+      location Hand on all
+      location Stock, Discard on table
+      card on Stock:
+        Rank(Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace)
+          for Suite(Diamonds, Hearts, Spades, Clubs)
+      deal 5 from top(Stock) private to Hand of all
+      {}
+    ",
+            rules
+        );
+        let game = test_rule_consume(&input, Rule::file, CGDSLParser::file).expect("parse failed");
+        information_flow_analysis(&game.to_graph())
+            .into_iter()
+            .map(|leak| {
+                (
+                    leak.clone(),
+                    input[leak.span().start..leak.span().end].to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Private cards of another player
+    match &leaks("flip Hand of next to face up")[..] {
+        [(InformationLeak::Revealed { location, .. }, text)] => {
+            assert_eq!(location, "Hand");
+            assert_eq!(text, "Hand of next");
+        }
+        other => panic!("expected one leak, got {:?}", other),
+    }
+    assert!(leaks("flip Hand to face up").is_empty());
+    assert!(leaks("move Hand of next face down to Discard").is_empty());
+
+    // Private cards on the table
+    assert!(matches!(
+        &leaks(
+            "move top(Stock) private to Discard
+             flip Discard to face up"
+        )[..],
+        [(InformationLeak::Revealed { location, .. }, _)] if location == "Discard"
+    ));
+    assert!(
+        leaks(
+            "move top(Stock) face down to Discard
+             flip Discard to face up"
+        )
+        .is_empty()
+    );
+
+    // The current player sees which branch is taken
+    match &leaks("if (\"Ace\" in Hand of next) { cycle to next }")[..] {
+        [(InformationLeak::HiddenCondition { location, .. }, text)] => {
+            assert_eq!(location, "Hand");
+            assert_eq!(text, "\"Ace\" in Hand of next");
+        }
+        other => panic!("expected one leak, got {:?}", other),
+    }
+    assert!(leaks("if (\"Ace\" in Hand) { cycle to next }").is_empty());
+    assert!(leaks("if (Hand of next empty) { cycle to next }").is_empty());
+
+    // Face down cards are hidden for everybody
+    assert!(matches!(
+        &leaks("if (Suite of top(Stock) == \"Hearts\") { cycle to next }")[..],
+        [(InformationLeak::HiddenCondition { location, .. }, _)] if location == "Stock"
+    ));
+    assert!(
+        leaks(
+            "move top(Stock) face up to Discard
+             if (Suite of top(Discard) == \"Hearts\") { cycle to next }"
+        )
+        .is_empty()
+    );
+}
+
+// ===========================================================================
+// Test FilterExpr evaluation
+// ===========================================================================
+#[test]
+fn test_filter_eval() {
+    let input = "
+      player P1, P2
+      location Hand on all
+      location Stock on table
+      card on Stock:
+        Rank(Ace, Two, Three, Four, Five)
+          for Suite(Hearts, Spades)
+      precedence RankOrder on Rank(Ace, Two, Three, Four, Five)
+      combo Sequence where ((size >= 3 and same Suite) and adjacent Rank using RankOrder)
+      combo Aces where ((size >= 2 and distinct Suite) and Rank is \"Ace\")
+      combo Loop where (size == 1 and Loop)
+      combo Counted where size == &I:Memory
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file)
+        .expect("parse failed")
+        .lower();
+    let evaluator = FilterEvaluator::from_game(&game);
+    let card = |rank, suite| Card::new([("Rank", rank), ("Suite", suite)]);
+
+    let run = [
+        card("Two", "Hearts"),
+        card("Three", "Hearts"),
+        card("Four", "Hearts"),
+    ];
+    let broken = [
+        card("Two", "Hearts"),
+        card("Four", "Hearts"),
+        card("Five", "Hearts"),
+    ];
+    let mixed = [
+        card("Two", "Hearts"),
+        card("Three", "Spades"),
+        card("Four", "Hearts"),
+    ];
+    fn refs(cards: &[Card]) -> Vec<&Card> {
+        cards.iter().collect()
+    }
+    assert_eq!(evaluator.matches_combo("Sequence", &refs(&run)), Ok(true));
+    assert_eq!(
+        evaluator.matches_combo("Sequence", &refs(&broken)),
+        Ok(false)
+    );
+    assert_eq!(
+        evaluator.matches_combo("Sequence", &refs(&mixed)),
+        Ok(false)
+    );
+
+    // Ace to Five of Hearts: 3 runs of three, 2 of four and 1 of five
+    let deck: Vec<Card> = game
+        .flows
+        .iter()
+        .flat_map(|flow| match flow {
+            FlowComponent::GameRule {
+                game_rule:
+                    GameRule::SetUp {
+                        setup: SetUpRule::CreateCardOnLocation { cards, .. },
+                    },
+            } => cards_of(cards),
+            _ => vec![],
+        })
+        .collect();
+    assert_eq!(deck.len(), 10);
+    let hearts: Vec<Card> = deck
+        .iter()
+        .filter(|c| c.get("Suite") == Some("Hearts"))
+        .cloned()
+        .collect();
+    let sequence = evaluator.combo("Sequence").unwrap();
+    assert_eq!(
+        evaluator.matching_subsets(sequence, &hearts).unwrap().len(),
+        6
+    );
+
+    // Ace to Five of Hearts covers five cards, Two to Five and both Aces cover six
+    let hand = [
+        card("Ace", "Hearts"),
+        card("Two", "Hearts"),
+        card("Three", "Hearts"),
+        card("Four", "Hearts"),
+        card("Five", "Hearts"),
+        card("Ace", "Spades"),
+        card("Three", "Spades"),
+    ];
+    let partition = evaluator.partition(&hand, &["Sequence", "Aces"]).unwrap();
+    assert_eq!(
+        partition.melds,
+        vec![
+            ("Aces".to_string(), vec![0, 5]),
+            ("Sequence".to_string(), vec![1, 2, 3, 4])
+        ]
+    );
+    assert_eq!(partition.rest, vec![6]);
+
+    // Errors
+    assert_eq!(
+        evaluator.matches_combo("Pair", &refs(&run)),
+        Err(FilterError::UnknownCombo("Pair".to_string()))
+    );
+    assert_eq!(
+        evaluator.matches_combo("Loop", &refs(&run[..1])),
+        Err(FilterError::CyclicCombo("Loop".to_string()))
+    );
+    assert!(matches!(
+        evaluator.matches_combo("Counted", &refs(&run)),
+        Err(FilterError::NeedsGameState(_))
+    ));
+    let no_rank = vec![Card::new([("Suite", "Hearts")]); 3];
+    assert!(matches!(
+        evaluator.matches_combo("Sequence", &refs(&no_rank)),
+        Err(FilterError::Table(TableError::MissingKey { key, .. })) if key == "Rank"
+    ));
+}
+
+// ===========================================================================
+// Test precedence and pointmap tables
+// ===========================================================================
+#[test]
+fn test_tables() {
+    let input = "
+      player P1, P2
+      location Hand on all
+      precedence RankOrder on Rank(Two, Three, Four, Queen, King, Ace)
+      precedence Trump (Suite Clubs, Suite Hearts, Rank Ace)
+      points Values on Rank(Two: 2, Three: 3, Four: 4, Queen: 10, King: 10, Ace: (1 + 10))
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file)
+        .expect("parse failed")
+        .lower();
+    let tables = Tables::from_game(&game).expect("tables failed");
+    let card = |rank, suite| Card::new([("Rank", rank), ("Suite", suite)]);
+    let (two, three, queen, king, ace) = (
+        card("Two", "Clubs"),
+        card("Three", "Hearts"),
+        card("Queen", "Clubs"),
+        card("King", "Hearts"),
+        card("Ace", "Clubs"),
+    );
+
+    // Comparison and ties
+    let rank_order = tables.precedence("RankOrder").unwrap();
+    assert_eq!(rank_order.compare(&ace, &king), Ok(Ordering::Greater));
+    assert_eq!(
+        rank_order.compare(&queen, &card("Queen", "Hearts")),
+        Ok(Ordering::Equal)
+    );
+    assert_eq!(rank_order.higher_than(&king, "Rank", "Queen"), Ok(true));
+    assert_eq!(rank_order.lower_than(&king, "Rank", "Queen"), Ok(false));
+    assert_eq!(
+        rank_order.extrema(&Extrema::Max, &[&queen, &king, &card("King", "Clubs")]),
+        Ok(vec![&king, &card("King", "Clubs")])
+    );
+    assert_eq!(
+        rank_order.extrema(&Extrema::Min, &[&queen, &two]),
+        Ok(vec![&two])
+    );
+
+    // A card has the highest rank of its Values
+    let trump = tables.precedence("Trump").unwrap();
+    assert_eq!(
+        trump.extrema(&Extrema::Max, &[&three, &ace, &king]),
+        Ok(vec![&ace])
+    );
+    assert_eq!(trump.compare(&three, &two), Ok(Ordering::Greater));
+
+    // Adjacent with and without wrap-around
+    assert_eq!(
+        rank_order.adjacent("Rank", &[&queen, &ace, &king]),
+        Ok(true)
+    );
+    assert_eq!(rank_order.adjacent("Rank", &[&king, &ace, &two]), Ok(false));
+    assert_eq!(rank_order.adjacent("Rank", &[&queen, &queen]), Ok(false));
+    let wrapped = rank_order.clone().with_wrap_around(true);
+    assert_eq!(wrapped.adjacent("Rank", &[&king, &ace, &two]), Ok(true));
+    assert_eq!(wrapped.adjacent("Rank", &[&ace, &two, &three]), Ok(true));
+    assert_eq!(wrapped.adjacent("Rank", &[&queen, &ace, &two]), Ok(false));
+
+    // Summation
+    let values = tables.pointmap("Values").unwrap();
+    assert_eq!(values.card_points(&ace), Ok(11));
+    assert_eq!(values.sum(&[&two, &queen, &ace]), Ok(23));
+    assert_eq!(values.sum(&[]), Ok(0));
+    assert_eq!(
+        values.extrema(&Extrema::Max, &[&queen, &king, &two]),
+        Ok(vec![&queen, &king])
+    );
+    assert_eq!(
+        values.extrema_points(&Extrema::Min, &[&queen, &two]),
+        Ok(Some(2))
+    );
+    assert_eq!(values.extrema_points(&Extrema::Min, &[]), Ok(None));
+
+    // Errors
+    let joker = Card::new([("Color", "Red")]);
+    assert!(matches!(
+        values.card_points(&joker),
+        Err(TableError::MissingKey { table, key, .. }) if table == "Values" && key == "Rank"
+    ));
+    assert!(matches!(
+        rank_order.adjacent("Rank", &[&card("Jack", "Clubs")]),
+        Err(TableError::UnknownValue { value, .. }) if value == "Jack"
+    ));
+    assert!(matches!(
+        tables.precedence("Values"),
+        Err(TableError::UnknownTable(_))
+    ));
+    let game = test_rule_consume(
+        "player P1, P2 location Hand on all points Bonus on Rank(Ace: &I:Memory)",
+        Rule::file,
+        CGDSLParser::file,
+    )
+    .expect("parse failed")
+    .lower();
+    assert!(matches!(
+        Tables::from_game(&game),
+        Err(TableError::NeedsGameState(_))
+    ));
+
+    // The FilterEvaluator uses the same tables
+    let mut evaluator = FilterEvaluator::from_game(&game_with_combo());
+    let run = [&king, &ace, &two];
+    assert_eq!(evaluator.matches_combo("Run", &run), Ok(false));
+    evaluator.set_wrap_around("RankOrder", true).unwrap();
+    assert_eq!(evaluator.matches_combo("Run", &run), Ok(true));
+    assert!(matches!(
+        evaluator.set_wrap_around("Values", true),
+        Err(FilterError::Table(TableError::UnknownTable(_)))
+    ));
+}
+
+fn game_with_combo() -> crate::ast::Game {
+    let input = "
+      player P1, P2
+      location Hand on all
+      precedence RankOrder on Rank(Two, Three, Four, Queen, King, Ace)
+      combo Run where (size == 3 and adjacent Rank using RankOrder)
+    ";
+    test_rule_consume(input, Rule::file, CGDSLParser::file)
+        .expect("parse failed")
+        .lower()
+}
+
+// ===========================================================================
+// Test tokens
+// ===========================================================================
+#[test]
+fn test_token_count() {
+    let game = "
+      player P1, P2
+      location Stack on all
+      location Pot on table
+      token 100 Chips on Stack
+      stage Bet for current until (tokens Chips in Stack of current == 0 or tokens Chips in Pot >= 50) {
+        place 10 Chips from Stack to Pot
+        cycle to next
+      }
+      memory Won on all
+      score tokens Chips in Stack to Won of all
+      winner is highest Won
+    ";
+    parse_ast_parse(game);
+    assert!(
+        symbol_validation(&test_rule_consume(game, Rule::file, CGDSLParser::file).unwrap()).is_ok()
+    );
+
+    let errors = |rules: &str| {
+        let input = format!(
+            "
+          player P1, P2
+          location Stack on all
+          token 100 Chips on Stack
+          {}
+        ",
+            rules
+        );
+        let game = test_rule_consume(&input, Rule::file, CGDSLParser::file).expect("parse failed");
+        symbol_validation(&game).err().unwrap_or_default()
+    };
+
+    // A Location is not a Token
+    match &errors("memory M on all M is tokens Stack in Stack")[..] {
+        [
+            SymbolError::WrongType {
+                var,
+                expected: GameType::Token,
+            },
+        ] => assert_eq!(var.id, "Stack"),
+        other => panic!("expected one error, got {:?}", other),
+    }
+    assert!(matches!(
+        &errors("place 1 Stack from Stack to Stack of next")[..],
+        [SymbolError::WrongType { .. }]
+    ));
+    // Unknown names are only reported once
+    assert!(matches!(
+        &errors("memory M on all M is tokens Coins in Stack")[..],
+        [SymbolError::NotInitialized { .. }]
+    ));
+}
+
+#[test]
+fn test_betting() {
+    let game = "
+      player P1, P2
+      location Stack on all
+      location Pot on table
+      token 100 Chips on Stack
+      memory Bid on all
+      stage Poker for current until tokens Chips in Pot >= 50 {
+        betting for all with Chips from Stack to Pot minimum 2
+        betting for others on Bid
+        cycle to next
+      }
+    ";
+    parse_ast_parse(game);
+    let game = test_rule_consume(game, Rule::file, CGDSLParser::file).expect("parse failed");
+    assert!(symbol_validation(&game).is_ok());
+
+    // Every round is a loop: entry -> turn -> acted -> entry, or settled
+    let fsm = game.to_lowered_graph();
+    let mut steps: Vec<_> = fsm
+        .states
+        .iter()
+        .flat_map(|(from, edges)| edges.iter().map(move |edge| (*from, edge)))
+        .filter_map(|(from, edge)| match &edge.payload {
+            Payload::Bet { round, step } => Some((round.to_string(), *step, from, edge.to)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(steps.len(), 12);
+    steps.retain(|(round, ..)| round == "betting for all with Chips from Stack to Pot minimum 2");
+    let edge = |step: BetStep| {
+        let (_, _, from, to) = steps.iter().find(|s| s.1 == step).expect("missing step");
+        (*from, *to)
+    };
+    let (entry, turn) = edge(BetStep::Open);
+    let (_, acted) = edge(BetStep::Call);
+    assert_eq!(edge(BetStep::Raise), (turn, acted));
+    assert_eq!(edge(BetStep::Fold), (turn, acted));
+    assert_eq!(edge(BetStep::NextPlayer), (acted, entry));
+    assert_eq!(edge(BetStep::Settled).0, entry);
+    assert!(fsm.structural_errors().is_empty());
+
+    // Betting writes the Pot, so the stage can end
+    assert!(termination_analysis(&game.to_graph()).is_empty());
+
+    // Folding sets players out of the stage
+    let input = "
+      location Stack on all
+      location Pot on table
+      token 100 Chips on Stack
+      stage Poker for current until previous out of stage {
+        betting for all with Chips from Stack to Pot
+        cycle to next
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    assert!(termination_analysis(&game.to_graph()).is_empty());
+
+    // The stake has to be a Token
+    let input = "
+      location Stack on all
+      location Pot on table
+      stage S for current until Pot empty {
+        betting for all with Stack from Stack to Pot
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    assert!(matches!(
+        &symbol_validation(&game).err().unwrap_or_default()[..],
+        [SymbolError::WrongType {
+            expected: GameType::Token,
+            ..
+        }]
+    ));
+
+    // Tokens move between Locations and a bid goes to a Memory
+    let wrong_type = |input: &str| {
+        let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+        symbol_validation(&game)
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|err| match err {
+                SymbolError::WrongType { var, expected } => (var.id, expected),
+                other => panic!("expected WrongType, got {:?}", other),
+            })
+            .collect::<Vec<_>>()
+    };
+    let input = "
+      location Hand on all
+      memory Bid on all
+      token 100 Chips on Hand
+      stage S for current until Hand empty {
+        betting for all with Chips from Bid to Chips
+      }
+    ";
+    assert_eq!(
+        wrong_type(input),
+        vec![
+            ("Bid".to_string(), GameType::Location),
+            ("Chips".to_string(), GameType::Location)
+        ]
+    );
+    let input = "
+      location Hand on all
+      stage S for current until Hand empty {
+        betting for all on Hand
+      }
+    ";
+    assert_eq!(
+        wrong_type(input),
+        vec![("Hand".to_string(), GameType::Memory)]
+    );
+}
+
+// ===========================================================================
+// Test tricks
+// ===========================================================================
+#[test]
+fn test_trick_taking() {
+    let input = "
+      player P1, P2, P3
+      location Hand, Trick on all
+      location Stock on table
+      card on Stock:
+        Rank(Seven, Ten, Ace)
+          for Suite(Hearts, Spades, Clubs)
+      precedence RankOrder on Rank(Seven, Ten, Ace)
+      memory Lead on table
+      stage Tricks for current until Hand of all empty {
+        stage Play for current until all out of stage {
+          move any from Hand where follow Suite is &S:Lead face up to Trick
+          set current out of stage
+          cycle to next
+        }
+        cycle to owner of winner of Trick of all using RankOrder trump \"Hearts\" follow Suite is &S:Lead
+      }
+    ";
+    parse_ast_parse(input);
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    assert!(symbol_validation(&game).is_ok());
+    assert!(semantic_validation(&game).is_none());
+
+    // The trump has to be a Value of the Key that is followed
+    let wrong_trump = input.replace("trump \"Hearts\"", "trump \"Ace\"");
+    let game = test_rule_consume(&wrong_trump, Rule::file, CGDSLParser::file).unwrap();
+    match &semantic_validation(&game).unwrap_or_default()[..] {
+        [SemanticError::NoCorrToType { key, .. }] => assert_eq!(key.id, "Suite"),
+        other => panic!("expected one error, got {:?}", other),
+    }
+
+    let evaluator = FilterEvaluator::from_game(&game.lower());
+    let card = |rank, suite| Card::new([("Rank", rank), ("Suite", suite)]);
+
+    // Follow suit if possible
+    let hand = [
+        card("Ace", "Clubs"),
+        card("Seven", "Spades"),
+        card("Ten", "Spades"),
+    ];
+    assert_eq!(evaluator.playable(&hand, "Suite", "Spades"), Ok(vec![1, 2]));
+    assert_eq!(
+        evaluator.playable(&hand, "Suite", "Hearts"),
+        Ok(vec![0, 1, 2])
+    );
+    fn refs(cards: &[Card]) -> Vec<&Card> {
+        cards.iter().collect()
+    }
+    let follow = FilterExpr::Aggregate {
+        aggregate: AggregateFilter::FollowKey {
+            key: "Suite".to_string(),
+            string: Box::new(StringExpr::Literal {
+                value: "Spades".to_string(),
+            }),
+        },
+    };
+    let follows = |cards: &[Card]| evaluator.matches_from(&follow, &refs(cards), &refs(&hand));
+    assert_eq!(follows(&hand[1..]), Ok(true));
+    assert_eq!(follows(&hand[..1]), Ok(false));
+    assert_eq!(follows(&hand[..2]), Ok(false));
+    assert_eq!(
+        evaluator.matching_subsets(&follow, &hand),
+        Ok(vec![vec![1], vec![1, 2], vec![2]])
+    );
+    // Without a Spade in the hand anything goes
+    assert_eq!(evaluator.matches(&follow, &refs(&hand[..1])), Ok(true));
+
+    // The highest card of the lead wins, unless there is a trump
+    let trick = [
+        card("Seven", "Spades"),
+        card("Ace", "Clubs"),
+        card("Ten", "Spades"),
+    ];
+    let winner = |trick: &[Card], trump| {
+        evaluator.trick_winner(trick, "RankOrder", "Suite", "Spades", trump)
+    };
+    assert_eq!(winner(&trick, None), Ok(Some(2)));
+    assert_eq!(winner(&trick, Some("Hearts")), Ok(Some(2)));
+    assert_eq!(winner(&trick, Some("Clubs")), Ok(Some(1)));
+    let trick = [
+        card("Ace", "Spades"),
+        card("Seven", "Hearts"),
+        card("Ten", "Hearts"),
+    ];
+    assert_eq!(winner(&trick, Some("Hearts")), Ok(Some(2)));
+    assert_eq!(winner(&[], Some("Hearts")), Ok(None));
+}
+
+// ===========================================================================
+// Test teams
+// ===========================================================================
+#[test]
+fn test_team_scoring() {
+    let rules = |rules: &str| {
+        format!(
+            "
+          player P1, P2, P3, P4
+          team Re with (P:P1, P:P3), Contra with (P:P2, P:P4)
+          memory Eyes on (T:Re, T:Contra)
+          stage Play for current 4 times {{
+            score 10 to team of current
+            score 3 to Eyes of T:Re
+            score 1 to Eyes of (T:Re, T:Contra)
+            cycle to next
+          }}
+          {}
+        ",
+            rules
+        )
+    };
+    let parse = |input: &str| {
+        test_rule_consume(input, Rule::file, CGDSLParser::file)
+            .expect("parse failed")
+            .lower()
+    };
+
+    for winner in [
+        "winner is highest team Eyes",
+        "winner is lowest team position",
+        "winner is highest team score",
+        "winner is T:Re",
+        "winner is (T:Re, T:Contra)",
+        "end game with winner T:Contra",
+        "end game with winner team of current",
+    ] {
+        parse_ast_parse(&rules(winner));
+    }
+
+    let game = parse(&rules("winner is highest team Eyes"));
+    let scoring: Vec<_> = game
+        .flows
+        .iter()
+        .flat_map(|flow| match flow {
+            FlowComponent::SeqStage { stage } => stage.flows.clone(),
+            flow => vec![flow.clone()],
+        })
+        .filter_map(|flow| match flow {
+            FlowComponent::GameRule {
+                game_rule: GameRule::Scoring { scoring },
+            } => Some(scoring),
+            _ => None,
+        })
+        .collect();
+    assert!(matches!(
+        &scoring[..],
+        [
+            ScoringRule::ScoreRule {
+                score_rule: ScoreRule::ScoreTeams {
+                    teams: Teams::Team { .. },
+                    ..
+                }
+            },
+            ScoringRule::ScoreRule {
+                score_rule: ScoreRule::ScoreMemoryTeams {
+                    teams: Teams::Team { .. },
+                    ..
+                }
+            },
+            ScoringRule::ScoreRule {
+                score_rule: ScoreRule::ScoreMemoryTeams {
+                    teams: Teams::TeamCollection { .. },
+                    ..
+                }
+            },
+            ScoringRule::WinnerRule {
+                winner_rule: WinnerRule::WinnerTeamWith {
+                    extrema: Extrema::Max,
+                    winner_type: WinnerType::Memory { .. },
+                }
+            },
+        ]
+    ));
+
+    // Players are still Players
+    assert!(matches!(
+        parse(&rules("end game with winner current")).flows.last(),
+        Some(FlowComponent::GameRule {
+            game_rule: GameRule::Action {
+                action: ActionRule::EndAction {
+                    end_type: EndType::GameWithWinner { .. }
+                }
+            }
+        })
+    ));
+
+    // A team is not a memory
+    let input = rules("score 1 to Re of T:Contra");
+    let game = test_rule_consume(&input, Rule::file, CGDSLParser::file).unwrap();
+    match &symbol_validation(&game).err().unwrap_or_default()[..] {
+        [
+            SymbolError::WrongType {
+                var,
+                expected: GameType::Memory,
+            },
+        ] => assert_eq!(var.id, "Re"),
+        other => panic!("expected one error, got {:?}", other),
+    }
+}
+
+// ===========================================================================
+// Test IR passes
+// ===========================================================================
+#[test]
+fn test_ir_passes() {
+    // Both options are the same, so the choice does not matter
+    let input = "
+      choose {
+        set current out of stage
+        or
+        set current out of stage
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    let (mut fsm, source_map) = game.to_lowered_graph_with_source_map();
+    let before = IrSize::of(&fsm);
+
+    let report = optimize(&mut fsm, &IrPass::default_pipeline());
+
+    assert_eq!(report.before(), Some(before));
+    let after = IrSize {
+        states: 2,
+        edges: 1,
+    };
+    assert_eq!(report.after(), Some(after));
+    assert!(fsm.structural_errors().is_empty());
+    assert_eq!(fsm.entry.raw(), 0);
+    assert_eq!(fsm.goal.raw(), 1);
+    assert!(matches!(
+        fsm.states[&fsm.entry][0].payload,
+        Payload::Action(_)
+    ));
+
+    // The remaining edge still points to both options in the source
+    let source_map = source_map.remap(&report.edge_map, &fsm);
+    let spans = source_map.spans_between(fsm.entry, fsm.goal);
+    assert_eq!(spans.len(), 2);
+    for span in spans {
+        assert_eq!(&input[span.start..span.end], "set current out of stage");
+    }
+
+    // Running the passes again does not change anything
+    let report = optimize(&mut fsm, &IrPass::default_pipeline());
+    assert!(report.passes.iter().all(|p| p.before == p.after));
+}
+
+#[test]
+fn test_ir_passes_keep_decisions() {
+    let input = "
+      stage Collect for current until Hand empty {
+        optional {
+          set current out of stage
+        }
+      }
+    ";
+    let game = test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+    let mut fsm = game.to_lowered_graph();
+    let before = IrSize::of(&fsm);
+
+    optimize(&mut fsm, &IrPass::default_pipeline());
+
+    // optional is a real decision (the body or nothing)
+    assert_eq!(IrSize::of(&fsm), before);
+    assert!(fsm.structural_errors().is_empty());
+}
+
+// ===========================================================================
+// Test diff
+// ===========================================================================
+#[test]
+fn test_diff() {
+    let old = "
+      location Hand on all
+      location Stock on table
+      combo Pair where size == 2
+      stage Play for current until Hand empty {
+        move top(Stock) private to Hand
+      }
+    ";
+    // Reordered setup and different formatting
+    let reordered = "
+      location Stock on table
+      location Hand on all
+      combo Pair where size   ==   2
+      stage Play for current until Hand empty { move top(Stock) private to Hand }
+    ";
+    let new = "
+      location Hand on all
+      location Stock on table
+      combo Pair where size == 3
+      stage Play for current until Hand empty {
+        move top(Stock) face up to Hand
+      }
+    ";
+    let parse =
+        |input| test_rule_consume(input, Rule::file, CGDSLParser::file).expect("parse failed");
+
+    assert!(diff_games(&parse(old), &parse(reordered)).is_empty());
+
+    let diff = diff_games(&parse(old), &parse(new));
+    assert!(diff.setup.is_empty());
+    assert!(matches!(diff.combos[..], [Change::Changed { .. }]));
+    assert!(matches!(diff.stages[..], [Change::Changed { .. }]));
+    match &diff.transitions["Play"][..] {
+        [Change::Changed { old, new }] => {
+            assert_eq!(old.from, new.from);
+            assert_eq!(new.label, "move top(Stock) face up to Hand");
+        }
+        other => panic!("expected one changed transition, got {:?}", other),
+    }
+
+    // One more SetUp-rule does not move the transitions
+    let added = old.replace(
+        "location Stock on table",
+        "location Stock on table\n      location Discard on table",
+    );
+    let diff = diff_games(&parse(old), &parse(&added));
+    assert!(matches!(diff.setup[..], [Change::Added(_)]));
+    assert!(diff.transitions.is_empty());
+}
+
 // ===========================================================================
 // Proptests
 // ===========================================================================
@@ -350,6 +1704,11 @@ proptest! {
       parse_ast_parse(&format!("{}", expr));
     }
 
+    #[test]
+    fn test_betting_round(expr in arb::<BettingRound>()) {
+      parse_ast_parse(&format!("{}", expr));
+    }
+
     #[test]
     fn test_game_rule(expr in arb::<GameRule>()) {
       parse_ast_parse(&format!("{}", expr));
@@ -1,9 +1,12 @@
 use std::collections::HashMap;
 
-use crate::ir::{GameFlowError, IrBuilder, SpannedPayload};
+use crate::card_count::{CardCountWarning, card_count_analysis};
+use crate::information_flow::{InformationLeak, information_flow_analysis};
+use crate::ir::{GameFlowError, Ir, IrBuilder, SpannedPayload};
 use crate::parser::Result;
 use crate::semantic::{SemanticError, SemanticVisitor};
 use crate::symbols::GameType;
+use crate::termination::{TerminationWarning, termination_analysis};
 use crate::{
     ast::ast_spanned::SGame,
     parser::{CGDSLParser, Rule},
@@ -45,6 +48,14 @@ pub fn semantic_validation(game: &SGame) -> Option<Vec<SemanticError>> {
 }
 
 pub fn program_validation(game: &SGame) -> Option<Vec<GameFlowError>> {
+    program_validation_with_ir(game).1
+}
+
+/// Same as program_validation, but also returns the IR that was checked,
+/// so that the analyses below do not have to build it again.
+pub fn program_validation_with_ir(
+    game: &SGame,
+) -> (Ir<SpannedPayload>, Option<Vec<GameFlowError>>) {
     let mut builder: IrBuilder<SpannedPayload> = IrBuilder::default();
 
     builder.build_ir(game);
@@ -60,8 +71,41 @@ pub fn program_validation(game: &SGame) -> Option<Vec<GameFlowError>> {
     }
 
     if result.is_empty() {
+        return (builder.fsm, None);
+    }
+
+    (builder.fsm, Some(result))
+}
+
+/// Warns about stages that can not end (see termination.rs).
+pub fn termination_validation(fsm: &Ir<SpannedPayload>) -> Option<Vec<TerminationWarning>> {
+    let warnings = termination_analysis(fsm);
+
+    if warnings.is_empty() {
+        return None;
+    }
+
+    Some(warnings)
+}
+
+/// Warns about moves and deals that need more cards than their Location has (see card_count.rs).
+pub fn card_count_validation(fsm: &Ir<SpannedPayload>) -> Option<Vec<CardCountWarning>> {
+    let warnings = card_count_analysis(fsm);
+
+    if warnings.is_empty() {
+        return None;
+    }
+
+    Some(warnings)
+}
+
+/// Warns about rules that show players cards they should not see (see information_flow.rs).
+pub fn information_flow_validation(fsm: &Ir<SpannedPayload>) -> Option<Vec<InformationLeak>> {
+    let leaks = information_flow_analysis(fsm);
+
+    if leaks.is_empty() {
         return None;
     }
 
-    return Some(result);
+    Some(leaks)
 }
@@ -1,6 +1,7 @@
 use front_end::{
-    ir::GameFlowError, parser::Rule, semantic::SemanticError, spans::OwnedSpan,
-    symbols::SymbolError,
+    card_count::CardCountWarning, information_flow::InformationLeak, ir::GameFlowError,
+    parser::Rule, semantic::SemanticError, spans::OwnedSpan, symbols::SymbolError,
+    termination::TerminationWarning,
 };
 use pest::error::LineColLocation;
 use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range};
@@ -94,6 +95,10 @@ pub fn symbol_error_to_diagnostics(symbol_error: &SymbolError) -> Diagnostic {
             value = var;
             message = format!("'{}' is defined multiple times", &value.id);
         }
+        SymbolError::WrongType { var, expected } => {
+            value = var;
+            message = format!("'{}' is not a {:?}", &value.id, expected);
+        }
     }
 
     Diagnostic {
@@ -187,6 +192,55 @@ pub fn program_error_to_diagnostics(program_error: &GameFlowError) -> Diagnostic
     }
 }
 
+/// Converts a termination warning (from the front_end) into a tower-lsp Diagnostic.
+/// The game is still valid, so this is only a warning.
+pub fn termination_warning_to_diagnostics(warning: &TerminationWarning) -> Diagnostic {
+    analysis_diagnostic(warning.span(), DiagnosticSeverity::WARNING, warning)
+}
+
+/// Converts a card count warning (from the front_end) into a tower-lsp Diagnostic.
+/// A Location that can be empty is common (e.g. drawing from the Stock), so that
+/// is only an information.
+pub fn card_count_warning_to_diagnostics(warning: &CardCountWarning) -> Diagnostic {
+    let severity = match warning {
+        CardCountWarning::NotEnoughCards { .. } => DiagnosticSeverity::WARNING,
+        CardCountWarning::MaybeNotEnoughCards { .. } => DiagnosticSeverity::INFORMATION,
+    };
+
+    analysis_diagnostic(warning.span(), severity, warning)
+}
+
+/// Converts an information leak (from the front_end) into a tower-lsp Diagnostic.
+/// Revealing the cards of another player can be intended (e.g. at the end of a round),
+/// so that is only an information.
+pub fn information_leak_to_diagnostics(leak: &InformationLeak) -> Diagnostic {
+    let severity = match leak {
+        InformationLeak::Revealed { .. } => DiagnosticSeverity::INFORMATION,
+        InformationLeak::HiddenCondition { .. } => DiagnosticSeverity::WARNING,
+    };
+
+    analysis_diagnostic(leak.span(), severity, leak)
+}
+
+/// The Diagnostic of an analysis of the IR (the message is the Display of the finding).
+fn analysis_diagnostic(
+    span: &OwnedSpan,
+    severity: DiagnosticSeverity,
+    message: impl std::fmt::Display,
+) -> Diagnostic {
+    Diagnostic {
+        range: to_range(span),
+        severity: Some(severity), // Defines the color/style
+        code: None,
+        source: Some("cgdsl-lsp".to_string()),
+        message: message.to_string(),
+        related_information: None,
+        tags: None,
+        data: None,
+        code_description: None,
+    }
+}
+
 /// Takes the Owned-Span struct and converts it into tower-lsp Range.
 pub fn to_range(span: &OwnedSpan) -> Range {
     // pest position starts at 1!
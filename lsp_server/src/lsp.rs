@@ -6,6 +6,7 @@ use crate::validation::{validate_document, validate_game, validate_parsing};
 use arc_swap::ArcSwapOption;
 use dashmap::DashMap;
 use front_end::ast::ast_spanned::SGame;
+use front_end::schema::IrEnvelope;
 use front_end::symbols::GameType;
 use front_end::validation::parse_document;
 use ropey::Rope;
@@ -369,9 +370,14 @@ impl LanguageServer for Backend {
                         jsonrpc::Error::internal_error()
                     })?;
 
-                // 5. Return the graph data to the extension 
+                // 5. Return the graph data (versioned envelope) to the extension
                 // (The extension can then use this to open the SVG automatically)
-                let json_value = serde_json::to_value(&graph)
+                let name = base_path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let envelope = IrEnvelope::new(&name, safe_ast, graph);
+                let json_value = serde_json::to_value(&envelope)
                     .map_err(|_| jsonrpc::Error::internal_error())?;
 
                 return Ok(Some(json_value));
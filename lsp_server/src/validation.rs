@@ -2,7 +2,10 @@ use crate::error_to_diagnostics::*;
 use front_end::{
     ast::ast_spanned::SGame,
     symbols::GameType,
-    validation::{parse_document, program_validation, semantic_validation, symbol_validation},
+    validation::{
+        card_count_validation, information_flow_validation, parse_document,
+        program_validation_with_ir, semantic_validation, symbol_validation, termination_validation,
+    },
 };
 use ropey::Rope;
 use std::collections::HashMap;
@@ -46,20 +49,39 @@ pub fn validate_document(ast: &SGame) -> Result<HashMap<GameType, Vec<String>>,
 ///
 /// Unlike `validate_document`, which focuses on symbols and semantics, this 
 /// checks for structural or "game-rule" violations defined in `program_validation`.
+/// If there are none, the IR built for that check is analysed as well: warnings
+/// about stages that can not end (`termination_validation`), about moves that
+/// need more cards than their Location has (`card_count_validation`) and about
+/// rules that show hidden cards (`information_flow_validation`).
 ///
 /// ### Returns
-/// * `Some(Vec<Diagnostic>)` if errors are found.
+/// * `Some(Vec<Diagnostic>)` if errors or warnings are found.
 /// * `None` if the game logic is valid.
 pub fn validate_game(ast: &SGame) -> Option<Vec<Diagnostic>> {
-    if let Some(errs) = program_validation(&ast) {
-        return Some(
-            errs.iter()
-                .map(|g| program_error_to_diagnostics(g))
-                .collect(),
-        );
+    let (fsm, errs) = program_validation_with_ir(ast);
+    if let Some(errs) = errs {
+        return Some(errs.iter().map(program_error_to_diagnostics).collect());
     }
 
-    return None;
+    let mut diagnostics = Vec::new();
+
+    if let Some(warnings) = termination_validation(&fsm) {
+        diagnostics.extend(warnings.iter().map(termination_warning_to_diagnostics));
+    }
+
+    if let Some(warnings) = card_count_validation(&fsm) {
+        diagnostics.extend(warnings.iter().map(card_count_warning_to_diagnostics));
+    }
+
+    if let Some(leaks) = information_flow_validation(&fsm) {
+        diagnostics.extend(leaks.iter().map(information_leak_to_diagnostics));
+    }
+
+    if diagnostics.is_empty() {
+        return None;
+    }
+
+    Some(diagnostics)
 }
 
 /// Converts a [`Rope`] to a string and attempts to parse it into an [`SGame`] AST.